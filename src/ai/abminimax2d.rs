@@ -1,50 +1,38 @@
 #![allow(dead_code)]
-use crate::ai_funcs::heuristics2d::{heuristic, is_terminal};
-use crate::board_structs::board::Board;
-use crate::utils::gamemove2d::GameMove2d;
-use crate::utils::pieces::WHITE;
-use std::collections::BinaryHeap;
-use std::sync::mpsc::{Receiver, TryRecvError};
+use crate::ai::heuristics2d::{heuristic, is_terminal};
+use crate::ai::search_control::SearchControl;
+use crate::board::Board;
+use crate::utils::gamemove1d::{GameMove1d, PassantTypes};
+use crate::utils::pieces::PieceColors;
+use std::cmp::Reverse;
 use std::time::Instant;
 
 pub struct ABMinimax {}
 
 impl ABMinimax {
-    pub fn find_move<T: Board>(&self, game: T, max_player: bool, time_left: u128) -> GameMove2d {
-        id_minimax(game, max_player, time_left)
+    pub fn find_move<T: Board>(&self, game: T, time_left: u128) -> GameMove1d {
+        id_minimax(game, time_left)
     }
 
-    pub fn uci_infinite_find_move<T: Board>(
+    pub fn uci_find_move<T: Board>(
         game: &T,
-        rx: Receiver<&str>,
-        start_moves: Option<Vec<GameMove2d>>,
-    ) -> GameMove2d {
+        start_moves: Option<Vec<GameMove1d>>,
+        control: &SearchControl,
+    ) -> GameMove1d {
         let start_time = Instant::now();
-        let max_player = game.get_curr_player();
-
-        let mut depth: usize = 1;
-        let mut best_move;
-        let move_search = if max_player == WHITE {
+        let move_search = if game.get_curr_player() == PieceColors::White {
             max_choice
         } else {
             min_choice
         };
-        let valid_moves = start_moves.unwrap_or(game.get_valid_moves());
-
-        best_move = move_search(game, depth, &valid_moves);
-        depth += 1;
+        let valid_moves = start_moves.unwrap_or_else(|| game.get_valid_moves());
+        let max_depth = control.limits.max_plies.unwrap_or(usize::MAX);
 
-        loop {
-            // Check if stop command received during last loop
-            match rx.try_recv() {
-                Ok("stop") => break,
-                Ok(_) => {}
-                Err(TryRecvError::Empty) => {}
-                Err(TryRecvError::Disconnected) => break,
-            }
-
-            best_move = move_search(game, depth, &valid_moves);
+        let mut depth: usize = 1;
+        let mut best_move = move_search(game, depth, &valid_moves);
+        while depth < max_depth && !control.should_abort() {
             depth += 1;
+            best_move = move_search(game, depth, &valid_moves);
 
             println!(
                 "info depth {} pv {} time {}",
@@ -53,121 +41,65 @@ impl ABMinimax {
                 start_time.elapsed().as_millis()
             );
         }
+        println!("bestmove {}", best_move);
         best_move
     }
+}
 
-    pub fn uci_timed_find_move<T: Board>(
-        game: &T,
-        time_to_search: u128,
-        rx: Receiver<&str>,
-        start_moves: Option<Vec<GameMove2d>>,
-        max_plies: Option<usize>,
-    ) -> GameMove2d {
-        let mut second_iter_time = Instant::now();
-        let start_time = Instant::now();
-        let max_player = game.get_curr_player();
-
-        let mut depth: usize = 1;
-        let mut out_of_time = false;
-        let mut best_move;
-        let move_search = if max_player == WHITE {
-            max_choice
-        } else {
-            min_choice
-        };
-        let valid_moves = start_moves.unwrap_or(game.get_valid_moves());
-
-        // println!("Engine Valid Moves: {:?}", valid_moves);
-
-        let max_depth = max_plies.unwrap_or(usize::MAX);
-
-        best_move = move_search(game, depth, &valid_moves);
-        depth += 1;
-        let mut last_iter_time = Instant::now();
-
-        // println!("Depth {} complete - Best Move: {}", depth, best_move);
-        while !out_of_time && depth <= max_depth {
-            // Check if stop command received during last loop
-            match rx.try_recv() {
-                Ok("stop") => break,
-                Ok(_) => {}
-                Err(TryRecvError::Empty) => {}
-                Err(TryRecvError::Disconnected) => break,
-            }
-
-            best_move = move_search(game, depth, &valid_moves);
-            depth += 1;
-
-            let this_iter_time = Instant::now();
-            let time_ratio = (this_iter_time - last_iter_time).as_millis()
-                / (last_iter_time - second_iter_time).as_millis();
-            let time_predict = (this_iter_time - last_iter_time).as_millis() * time_ratio;
-
-            second_iter_time = last_iter_time;
-            last_iter_time = this_iter_time;
-            if this_iter_time.elapsed().as_millis() + time_predict > time_to_search {
-                out_of_time = true;
-            }
-
-            println!(
-                "info depth {} pv {} time {}",
-                depth,
-                best_move,
-                start_time.elapsed().as_millis()
-            );
-        }
-        best_move
+// Same preference the 2D moves used to sort by: en passant, captures, then castles and
+// promotions
+fn priority(mov: &GameMove1d) -> i32 {
+    let mut score = 0;
+    if mov.castle.is_some() {
+        score += 2;
+    }
+    if mov.capture {
+        score += 3;
+    }
+    if mov.promote.is_some() {
+        score += 2;
+    }
+    if let Some(PassantTypes::PassantCapture(_)) = mov.passant {
+        score += 15;
     }
+    score
 }
 
-fn max_choice<T: Board>(game: &T, depth: usize, available_moves: &Vec<GameMove2d>) -> GameMove2d {
+fn ordered(moves: &[GameMove1d]) -> Vec<GameMove1d> {
+    let mut moves = moves.to_vec();
+    moves.sort_by_key(|mov| Reverse(priority(mov)));
+    moves
+}
+
+fn max_choice<T: Board>(game: &T, depth: usize, available_moves: &[GameMove1d]) -> GameMove1d {
     let mut best_move = available_moves[0];
     let mut best_value = i32::MIN;
-    let moves = BinaryHeap::from_iter(available_moves.iter());
-    for action in moves {
-        let minimax_value = min_value(
-            game.clone().make_move(action),
-            depth,
-            &i32::MIN,
-            &i32::MAX,
-            available_moves,
-        );
+    for action in ordered(available_moves) {
+        let minimax_value = min_value(&game.make_move(&action), depth, &i32::MIN, &i32::MAX);
         if best_value < minimax_value {
             best_value = minimax_value;
-            best_move = *action;
+            best_move = action;
         }
     }
     best_move
 }
 
-fn min_choice<T: Board>(game: &T, depth: usize, available_moves: &Vec<GameMove2d>) -> GameMove2d {
+fn min_choice<T: Board>(game: &T, depth: usize, available_moves: &[GameMove1d]) -> GameMove1d {
     let mut best_move = available_moves[0];
     let mut best_value = i32::MAX;
-    let moves = BinaryHeap::from_iter(available_moves.iter());
-    for action in moves {
-        let minimax_value = max_value(
-            game.clone().make_move(action),
-            depth,
-            &i32::MIN,
-            &i32::MAX,
-            available_moves,
-        );
+    for action in ordered(available_moves) {
+        let minimax_value = max_value(&game.make_move(&action), depth, &i32::MIN, &i32::MAX);
         if best_value > minimax_value {
             best_value = minimax_value;
-            best_move = *action;
+            best_move = action;
         }
     }
     best_move
 }
 
-fn max_value<T: Board>(
-    game: T,
-    depth: usize,
-    alpha: &i32,
-    beta: &i32,
-    available_moves: &Vec<GameMove2d>,
-) -> i32 {
-    if let Some(winner) = is_terminal(&game, available_moves) {
+fn max_value<T: Board>(game: &T, depth: usize, alpha: &i32, beta: &i32) -> i32 {
+    let available_moves = game.get_valid_moves();
+    if let Some(winner) = is_terminal(game, &available_moves) {
         return winner;
     }
     if depth == 0 {
@@ -175,15 +107,8 @@ fn max_value<T: Board>(
     }
     let mut value = i32::MIN;
     let mut alpha = *alpha;
-    let moves = BinaryHeap::from_iter(available_moves.iter());
-    for action in moves {
-        let action_val = min_value(
-            game.clone().make_move(action),
-            depth - 1,
-            &alpha,
-            beta,
-            available_moves,
-        );
+    for action in ordered(&available_moves) {
+        let action_val = min_value(&game.make_move(&action), depth - 1, &alpha, beta);
         value = i32::max(value, action_val);
         if value >= *beta {
             return value;
@@ -193,14 +118,9 @@ fn max_value<T: Board>(
     value
 }
 
-fn min_value<T: Board>(
-    game: T,
-    depth: usize,
-    alpha: &i32,
-    beta: &i32,
-    available_moves: &Vec<GameMove2d>,
-) -> i32 {
-    if let Some(winner) = is_terminal(&game, available_moves) {
+fn min_value<T: Board>(game: &T, depth: usize, alpha: &i32, beta: &i32) -> i32 {
+    let available_moves = game.get_valid_moves();
+    if let Some(winner) = is_terminal(game, &available_moves) {
         return winner;
     }
     if depth == 0 {
@@ -208,15 +128,8 @@ fn min_value<T: Board>(
     }
     let mut value = i32::MAX;
     let mut beta = *beta;
-    let moves = BinaryHeap::from_iter(available_moves.iter());
-    for action in moves {
-        let action_val = max_value(
-            game.clone().make_move(action),
-            depth - 1,
-            alpha,
-            &beta,
-            available_moves,
-        );
+    for action in ordered(&available_moves) {
+        let action_val = max_value(&game.make_move(&action), depth - 1, alpha, &beta);
         value = i32::min(value, action_val);
         if value <= *alpha {
             return value;
@@ -226,31 +139,31 @@ fn min_value<T: Board>(
     value
 }
 
-fn id_minimax<T: Board>(game: T, max_player: bool, time_left: u128) -> GameMove2d {
+fn id_minimax<T: Board>(game: T, time_left: u128) -> GameMove1d {
     let mut second_iter_time = Instant::now();
     let available_time = time_left / 20;
 
     let mut depth: usize = 1;
     let mut done = false;
     let mut best_move;
-    let move_search = if max_player == WHITE {
+    let move_search = if game.get_curr_player() == PieceColors::White {
         max_choice
     } else {
         min_choice
     };
 
-    best_move = move_search(&game, depth, &game.get_valid_moves());
+    let valid_moves = game.get_valid_moves();
+    best_move = move_search(&game, depth, &valid_moves);
     depth += 1;
     let mut last_iter_time = Instant::now();
 
     while !done {
-        let valid_moves = game.get_valid_moves();
         best_move = move_search(&game, depth, &valid_moves);
         depth += 1;
 
         let this_iter_time = Instant::now();
         let time_ratio = (this_iter_time - last_iter_time).as_nanos()
-            / (last_iter_time - second_iter_time).as_nanos();
+            / (last_iter_time - second_iter_time).as_nanos().max(1);
         let time_predict = (this_iter_time - last_iter_time).as_nanos() * time_ratio;
 
         second_iter_time = last_iter_time;
@@ -269,3 +182,16 @@ fn id_minimax<T: Board>(game: T, max_player: bool, time_left: u128) -> GameMove2
     }
     best_move
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::array2d::Array2D;
+
+    #[test]
+    fn test_finds_mate_in_one() {
+        let game = Array2D::setup_board(Some("6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1")).unwrap();
+        let best_move = max_choice(&game, 1, &game.get_valid_moves());
+        assert_eq!(best_move.to_string(), "d1d8");
+    }
+}
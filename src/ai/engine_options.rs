@@ -0,0 +1,303 @@
+use std::fmt;
+
+use crate::ai::transposition::DEFAULT_HASH_MB;
+use crate::utils::chess_errors::ChessError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OptionKind {
+    Check { default: bool },
+    Spin { default: i64, min: i64, max: i64 },
+    Button,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EngineOption {
+    pub name: &'static str,
+    pub kind: OptionKind,
+}
+
+// Every option advertised to the GUI after `id`
+pub const ENGINE_OPTIONS: [EngineOption; 11] = [
+    EngineOption {
+        name: "Hash",
+        kind: OptionKind::Spin {
+            default: DEFAULT_HASH_MB as i64,
+            min: 1,
+            max: 32768,
+        },
+    },
+    EngineOption {
+        name: "Clear Hash",
+        kind: OptionKind::Button,
+    },
+    EngineOption {
+        name: "Threads",
+        kind: OptionKind::Spin {
+            default: 1,
+            min: 1,
+            max: 512,
+        },
+    },
+    EngineOption {
+        name: "MultiPV",
+        kind: OptionKind::Spin {
+            default: 1,
+            min: 1,
+            max: 256,
+        },
+    },
+    EngineOption {
+        name: "Ponder",
+        kind: OptionKind::Check { default: false },
+    },
+    EngineOption {
+        name: "Move Overhead",
+        kind: OptionKind::Spin {
+            default: 10,
+            min: 0,
+            max: 5000,
+        },
+    },
+    EngineOption {
+        name: "UCI_ShowWDL",
+        kind: OptionKind::Check { default: false },
+    },
+    EngineOption {
+        name: "NullMove",
+        kind: OptionKind::Check { default: true },
+    },
+    EngineOption {
+        name: "LateMoveReductions",
+        kind: OptionKind::Check { default: true },
+    },
+    EngineOption {
+        name: "ReverseFutility",
+        kind: OptionKind::Check { default: true },
+    },
+    EngineOption {
+        name: "Futility",
+        kind: OptionKind::Check { default: true },
+    },
+];
+
+impl fmt::Display for EngineOption {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            OptionKind::Check { default } => {
+                write!(
+                    f,
+                    "option name {} type check default {}",
+                    self.name, default
+                )
+            }
+            OptionKind::Spin { default, min, max } => write!(
+                f,
+                "option name {} type spin default {} min {} max {}",
+                self.name, default, min, max
+            ),
+            OptionKind::Button => write!(f, "option name {} type button", self.name),
+        }
+    }
+}
+
+/// Side effects the engine has to perform after an option changed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OptionAction {
+    None,
+    ResizeHash(usize),
+    ClearHash,
+}
+
+/// Selective search techniques, each can be turned off to measure what it is worth
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pruning {
+    pub null_move: bool,
+    pub late_move_reductions: bool,
+    pub reverse_futility: bool,
+    pub futility: bool,
+}
+
+impl Pruning {
+    pub const ALL: Pruning = Pruning {
+        null_move: true,
+        late_move_reductions: true,
+        reverse_futility: true,
+        futility: true,
+    };
+    pub const NONE: Pruning = Pruning {
+        null_move: false,
+        late_move_reductions: false,
+        reverse_futility: false,
+        futility: false,
+    };
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EngineOptions {
+    pub hash_mb: usize,
+    pub threads: usize,
+    pub multi_pv: usize,
+    pub ponder: bool,
+    pub move_overhead: u128,
+    pub show_wdl: bool,
+    pub pruning: Pruning,
+}
+
+impl Default for EngineOptions {
+    fn default() -> Self {
+        let mut options = EngineOptions {
+            hash_mb: 0,
+            threads: 0,
+            multi_pv: 0,
+            ponder: false,
+            move_overhead: 0,
+            show_wdl: false,
+            pruning: Pruning::NONE,
+        };
+        for option in ENGINE_OPTIONS {
+            match option.kind {
+                OptionKind::Check { default } => options.assign(option.name, default as i64),
+                OptionKind::Spin { default, .. } => options.assign(option.name, default),
+                OptionKind::Button => {}
+            }
+        }
+        options
+    }
+}
+
+impl EngineOptions {
+    /// Applies `setoption name <name> value <value>`, names are matched case-insensitively
+    pub fn set(&mut self, name: &str, value: Option<&str>) -> Result<OptionAction, ChessError> {
+        let option = ENGINE_OPTIONS
+            .iter()
+            .find(|option| option.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| ChessError::UnknownOptionError {
+                name: name.to_string(),
+            })?;
+        let invalid_value = || ChessError::OptionValueError {
+            name: option.name.to_string(),
+            value: value.unwrap_or_default().to_string(),
+        };
+
+        let parsed = match option.kind {
+            OptionKind::Button => return Ok(OptionAction::ClearHash),
+            OptionKind::Check { .. } => match value.map(str::to_ascii_lowercase).as_deref() {
+                Some("true") => 1,
+                Some("false") => 0,
+                _ => return Err(invalid_value()),
+            },
+            OptionKind::Spin { min, max, .. } => {
+                let number = value
+                    .and_then(|x| x.parse::<i64>().ok())
+                    .ok_or_else(invalid_value)?;
+                if !(min..=max).contains(&number) {
+                    return Err(invalid_value());
+                }
+                number
+            }
+        };
+
+        self.assign(option.name, parsed);
+        match option.name {
+            "Hash" => Ok(OptionAction::ResizeHash(self.hash_mb)),
+            _ => Ok(OptionAction::None),
+        }
+    }
+
+    fn assign(&mut self, name: &str, value: i64) {
+        match name {
+            "Hash" => self.hash_mb = value as usize,
+            "Threads" => self.threads = value as usize,
+            "MultiPV" => self.multi_pv = value as usize,
+            "Ponder" => self.ponder = value != 0,
+            "Move Overhead" => self.move_overhead = value as u128,
+            "UCI_ShowWDL" => self.show_wdl = value != 0,
+            "NullMove" => self.pruning.null_move = value != 0,
+            "LateMoveReductions" => self.pruning.late_move_reductions = value != 0,
+            "ReverseFutility" => self.pruning.reverse_futility = value != 0,
+            "Futility" => self.pruning.futility = value != 0,
+            _ => {}
+        }
+    }
+}
+
+/// Splits the arguments of a `setoption` command into its name and value, both of
+/// which may contain spaces
+pub fn parse_setoption(args: &[&str]) -> Option<(String, Option<String>)> {
+    let name_index = args.iter().position(|&x| x == "name")?;
+    let value_index = args.iter().position(|&x| x == "value");
+    let name_end = value_index.unwrap_or(args.len());
+    if name_end <= name_index + 1 {
+        return None;
+    }
+    let name = args[name_index + 1..name_end].join(" ");
+    let value = value_index.map(|index| args[index + 1..].join(" "));
+    Some((name, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_option_declarations() {
+        assert_eq!(
+            ENGINE_OPTIONS[0].to_string(),
+            "option name Hash type spin default 16 min 1 max 32768"
+        );
+        assert_eq!(
+            ENGINE_OPTIONS[1].to_string(),
+            "option name Clear Hash type button"
+        );
+        assert_eq!(
+            ENGINE_OPTIONS[4].to_string(),
+            "option name Ponder type check default false"
+        );
+    }
+
+    #[test]
+    fn test_parse_setoption() {
+        let args: Vec<&str> = "setoption name Move Overhead value 30".split(' ').collect();
+        assert_eq!(
+            parse_setoption(&args),
+            Some(("Move Overhead".to_string(), Some("30".to_string())))
+        );
+        let args: Vec<&str> = "setoption name Clear Hash".split(' ').collect();
+        assert_eq!(
+            parse_setoption(&args),
+            Some(("Clear Hash".to_string(), None))
+        );
+        assert_eq!(parse_setoption(&["setoption", "name"]), None);
+    }
+
+    #[test]
+    fn test_set_options() {
+        let mut options = EngineOptions::default();
+        assert_eq!(options.hash_mb, DEFAULT_HASH_MB);
+        assert_eq!(options.move_overhead, 10);
+
+        assert_eq!(
+            options.set("hash", Some("64")).unwrap(),
+            OptionAction::ResizeHash(64)
+        );
+        assert_eq!(
+            options.set("Clear Hash", None).unwrap(),
+            OptionAction::ClearHash
+        );
+        options.set("UCI_ShowWDL", Some("true")).unwrap();
+        assert!(options.show_wdl);
+        assert_eq!(options.pruning, Pruning::ALL);
+        options.set("nullmove", Some("false")).unwrap();
+        assert!(!options.pruning.null_move);
+
+        assert!(options.set("Threads", Some("0")).is_err());
+        assert!(options.set("Ponder", Some("maybe")).is_err());
+        assert!(options.set("Contempt", Some("10")).is_err());
+        // Castling is only generated for standard chess, so Chess960 is not offered
+        assert!(matches!(
+            options.set("UCI_Chess960", Some("true")),
+            Err(ChessError::UnknownOptionError { .. })
+        ));
+        assert_eq!(options.threads, 1);
+    }
+}
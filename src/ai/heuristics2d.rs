@@ -1,15 +1,18 @@
-use crate::utils::pieces::{PieceColors::*, PieceTypes, PieceTypes::*, Pieces, BLACK, WHITE};
+use crate::board::Board;
+use crate::utils::checks::Checks;
+use crate::utils::pieces::{PieceColors::*, PieceTypes, PieceTypes::*, Pieces};
 
-use crate::utils::gamemove2d::GameMove2d;
+use crate::utils::gamemove1d::GameMove1d;
 use std::collections::HashMap;
 
-pub fn is_terminal<T: crate::board_structs::board::Board>(
-    game: &T,
-    available_moves: &Vec<GameMove2d>,
-) -> Option<i32> {
+// Scores are from white's point of view, so the side that is mated decides the sign
+pub fn is_terminal<T: Board>(game: &T, available_moves: &[GameMove1d]) -> Option<i32> {
     if available_moves.is_empty() {
         if game.get_check().is_some() {
-            Some([i32::MIN + 1, i32::MAX - 1][game.get_curr_player() as usize])
+            match game.get_curr_player() {
+                Black => Some(i32::MAX - 1),
+                _ => Some(i32::MIN + 1),
+            }
         } else {
             Some(0)
         }
@@ -149,6 +152,7 @@ const EG_KING_TABLE: [[i32; 8]; 8] = [
     [-74, -35, -18, -18, -11, 15, 4, -17],
 ];
 
+#[allow(dead_code)]
 const SQUARE_CONTROL_TABLE: [[f32; 8]; 8] = [
     [0.50, 0.50, 0.50, 0.50, 0.50, 0.50, 0.50, 0.50],
     [0.75, 0.75, 0.75, 0.75, 0.75, 0.75, 0.75, 0.75],
@@ -281,7 +285,7 @@ fn get_position_value(piece: &Pieces, [row, col]: [usize; 2], game_time: bool) -
     }
 }
 
-pub fn heuristic<T: crate::board_structs::board::Board>(game: T) -> i32 {
+pub fn heuristic<T: Board>(game: &T) -> i32 {
     let mut result = 0;
     // let game_time = if game.get_board_as_2d()
     //     .iter()
@@ -297,12 +301,11 @@ pub fn heuristic<T: crate::board_structs::board::Board>(game: T) -> i32 {
     let game_time = MIDGAME;
     // Count Pieces and score pieces on position
     let mut piece_counts: HashMap<Pieces, i32> = HashMap::new();
-    for (i, line) in game.get_board_as_2d().iter().enumerate() {
-        for (j, piece) in line.iter().enumerate() {
-            *piece_counts.entry(*piece).or_default() += 1;
-            //Get positional advantage of the piece
-            result += get_position_value(piece, [i, j], game_time);
-        }
+    for (pos, piece) in game.pieces() {
+        *piece_counts.entry(piece).or_default() += 1;
+        //Get positional advantage of the piece
+        let square = [pos.value / 10 - 2, pos.value % 10 - 1];
+        result += get_position_value(&piece, square, game_time);
     }
     // Add material value to evaluation
     for (piece, number) in piece_counts.iter() {
@@ -338,8 +341,8 @@ pub fn heuristic<T: crate::board_structs::board::Board>(game: T) -> i32 {
     // Consider checks
     if let Some(player) = &game.get_check() {
         match *player {
-            BLACK => result += 50,
-            WHITE => result -= 50,
+            Checks::Black => result += 50,
+            Checks::White => result -= 50,
         }
     }
     // Prefer to have two bishops
@@ -361,16 +364,16 @@ pub fn heuristic<T: crate::board_structs::board::Board>(game: T) -> i32 {
     }
     //Add value to having castle rights
     let rights = game.get_castle_rights();
-    if rights[0] {
+    if rights.white_king {
         result += 30;
     }
-    if rights[1] {
+    if rights.white_queen {
         result += 30;
     }
-    if rights[2] {
+    if rights.black_king {
         result -= 30;
     }
-    if rights[3] {
+    if rights.black_queen {
         result -= 30;
     }
 
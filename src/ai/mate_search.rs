@@ -0,0 +1,189 @@
+use crate::ai::search_control::SearchControl;
+use crate::board::Board;
+use crate::utils::gamemove1d::GameMove1d;
+
+// How many nodes are searched between checks of the search control
+const POLL_INTERVAL: usize = 1024;
+
+// Depth-limited AND/OR search that only answers whether the side to move can force mate,
+// which prunes far harder than a scored search once a refutation is found
+pub struct MateSearch<'a> {
+    control: Option<&'a SearchControl>,
+    pub nodes: usize,
+    pub stopped: bool,
+}
+
+impl<'a> MateSearch<'a> {
+    pub fn new(control: Option<&'a SearchControl>) -> Self {
+        MateSearch {
+            control,
+            nodes: 0,
+            stopped: false,
+        }
+    }
+
+    /// Finds the shortest forced mate in at most `mate_moves` moves, returning its length
+    /// in moves and the principal variation leading to it
+    pub fn find_mate<B: Board>(
+        &mut self,
+        game: &B,
+        mate_moves: usize,
+        available_moves: Option<Vec<GameMove1d>>,
+    ) -> Option<(usize, Vec<GameMove1d>)> {
+        let root_moves = available_moves.unwrap_or_else(|| game.get_valid_moves());
+        for moves in 1..=mate_moves {
+            if let Some(mate_move) = self.attacker_wins(game, &root_moves, moves) {
+                let pv = self.principal_variation(game, mate_move, moves);
+                return Some((moves, pv));
+            }
+            if self.stopped {
+                break;
+            }
+        }
+        None
+    }
+
+    // Returns a move that forces mate within `moves_left` moves of the side to move
+    fn attacker_wins<B: Board>(
+        &mut self,
+        game: &B,
+        moves: &[GameMove1d],
+        moves_left: usize,
+    ) -> Option<GameMove1d> {
+        if !self.visit_node() {
+            return None;
+        }
+
+        // Try checks first, they are the most likely to lead to mate
+        let mut children: Vec<(GameMove1d, B, bool)> = moves
+            .iter()
+            .map(|mv| {
+                let child = game.make_move(mv);
+                let gives_check = child.get_check().is_some();
+                (*mv, child, gives_check)
+            })
+            .filter(|(_, _, gives_check)| *gives_check || moves_left > 1)
+            .collect();
+        children.sort_by_key(|(_, _, gives_check)| !gives_check);
+
+        for (mv, child, _) in children {
+            if self.defender_loses(&child, moves_left - 1) {
+                return Some(mv);
+            }
+            if self.stopped {
+                return None;
+            }
+        }
+        None
+    }
+
+    // True if every reply of the side to move runs into mate within `moves_left` moves
+    fn defender_loses<B: Board>(&mut self, game: &B, moves_left: usize) -> bool {
+        let replies = game.get_valid_moves();
+        if replies.is_empty() {
+            // Checkmate rather than stalemate
+            return game.get_check().is_some();
+        }
+        if moves_left == 0 {
+            return false;
+        }
+        for reply in replies {
+            let child = game.make_move(&reply);
+            if self
+                .attacker_wins(&child, &child.get_valid_moves(), moves_left)
+                .is_none()
+            {
+                return false;
+            }
+        }
+        true
+    }
+
+    // Builds the mating line by letting the defender pick the reply that delays mate longest
+    fn principal_variation<B: Board>(
+        &mut self,
+        game: &B,
+        mate_move: GameMove1d,
+        moves: usize,
+    ) -> Vec<GameMove1d> {
+        let mut pv = vec![mate_move];
+        let mut position = game.make_move(&mate_move);
+        let mut moves_left = moves - 1;
+        while moves_left > 0 && !self.stopped {
+            let mut longest: Option<(usize, GameMove1d, GameMove1d)> = None;
+            for reply in position.get_valid_moves() {
+                let child = position.make_move(&reply);
+                let child_moves = child.get_valid_moves();
+                if let Some((length, attack)) = (1..=moves_left).find_map(|length| {
+                    self.attacker_wins(&child, &child_moves, length)
+                        .map(|attack| (length, attack))
+                }) {
+                    if longest.is_none_or(|(longest, _, _)| length > longest) {
+                        longest = Some((length, reply, attack));
+                    }
+                }
+            }
+            let Some((length, reply, attack)) = longest else {
+                break;
+            };
+            pv.push(reply);
+            pv.push(attack);
+            position = position.make_move(&reply).make_move(&attack);
+            moves_left = length - 1;
+        }
+        pv
+    }
+
+    fn visit_node(&mut self) -> bool {
+        if self.stopped {
+            return false;
+        }
+        self.nodes += 1;
+        if self.nodes.is_multiple_of(POLL_INTERVAL)
+            && self.control.is_some_and(SearchControl::should_abort)
+        {
+            self.stopped = true;
+        }
+        !self.stopped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::mailbox::Mailbox;
+
+    fn moves_to_string(moves: &[GameMove1d]) -> Vec<String> {
+        moves.iter().map(|mv| mv.to_string()).collect()
+    }
+
+    #[test]
+    fn test_mate_in_one() {
+        let game = Mailbox::setup_board(Some("6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1")).unwrap();
+        let mut search = MateSearch::new(None);
+        let (moves, pv) = search.find_mate(&game, 3, None).unwrap();
+        assert_eq!(moves, 1);
+        assert_eq!(moves_to_string(&pv), ["d1d8"]);
+    }
+
+    #[test]
+    fn test_mate_in_two_needs_quiet_move() {
+        // Ra6 threatens b7#, and bxa6 opens the file for the same mate
+        let game = Mailbox::setup_board(Some("kbK5/pp6/1P6/8/8/8/8/R7 w - - 0 1")).unwrap();
+        let mut search = MateSearch::new(None);
+        let (moves, pv) = search.find_mate(&game, 2, None).unwrap();
+        assert_eq!(moves, 2);
+        assert_eq!(pv.len(), 3);
+        assert_eq!(pv[0].to_string(), "a1a6");
+    }
+
+    #[test]
+    fn test_stalemate_is_not_mate() {
+        // Qb6 is stalemate, only the king-protected Qb7 mates
+        let game = Mailbox::setup_board(Some("k7/8/2K5/8/8/8/8/1Q6 w - - 0 1")).unwrap();
+        let mut search = MateSearch::new(None);
+        let (moves, pv) = search.find_mate(&game, 1, None).unwrap();
+        assert_eq!(moves, 1);
+        assert_eq!(moves_to_string(&pv), ["b1b7"]);
+    }
+}
@@ -1,5 +1,12 @@
-// pub mod abminimax2d;
-// pub mod heuristics2d;
+pub mod abminimax2d;
+pub mod engine_options;
+pub mod heuristics2d;
 pub mod manual;
+pub mod mate_search;
+pub mod move_ordering;
 pub mod negamax_mailbox;
-// pub mod random2d;
+pub mod random2d;
+pub mod score;
+pub mod search_control;
+pub mod time_manager;
+pub mod transposition;
@@ -0,0 +1,241 @@
+use crate::board::Board;
+use crate::utils::gamemove1d::{GameMove1d, PassantTypes};
+use crate::utils::pieces::{PieceColors, PieceTypes};
+
+const BOARD_SIZE: usize = 120;
+const KILLERS_PER_PLY: usize = 2;
+
+// Every move of an earlier stage sorts before any move of a later stage: hash move,
+// captures that do not lose material, promotions, killers, quiet moves by their history
+// score and finally the losing captures
+const HASH_MOVE_SCORE: i32 = i32::MAX;
+const CAPTURE_SCORE: i32 = 1 << 30;
+const PROMOTION_SCORE: i32 = 1 << 29;
+const KILLER_SCORE: i32 = 1 << 28;
+const BAD_CAPTURE_SCORE: i32 = -(1 << 30);
+// History is halved once an entry passes this, so quiet moves stay below the killers and
+// older results fade out over a long search
+const HISTORY_LIMIT: i32 = 1 << 20;
+
+/// Counts how often a beta cutoff came from the first move searched at a node, which is
+/// the usual measure of how good the move ordering is
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OrderingStats {
+    pub cutoffs: usize,
+    pub first_move_cutoffs: usize,
+}
+
+impl OrderingStats {
+    pub fn first_move_rate(&self) -> f64 {
+        if self.cutoffs == 0 {
+            return 0.0;
+        }
+        self.first_move_cutoffs as f64 / self.cutoffs as f64
+    }
+}
+
+/// Killer moves and butterfly history collected during a single search
+pub struct MoveOrdering {
+    // Quiet moves that caused a cutoff at each ply, most recent first
+    killers: Vec<[Option<GameMove1d>; KILLERS_PER_PLY]>,
+    // Cutoff scores of quiet moves indexed by side to move, start and end square
+    history: Vec<i32>,
+    pub stats: OrderingStats,
+}
+
+impl Default for MoveOrdering {
+    fn default() -> Self {
+        MoveOrdering {
+            killers: Vec::new(),
+            history: vec![0; 2 * BOARD_SIZE * BOARD_SIZE],
+            stats: OrderingStats::default(),
+        }
+    }
+}
+
+impl MoveOrdering {
+    /// Orders `moves` of the node at `ply` for searching
+    pub fn picker<B: Board>(
+        &self,
+        game: &B,
+        moves: Vec<GameMove1d>,
+        hash_move: Option<GameMove1d>,
+        ply: usize,
+    ) -> MovePicker {
+        let killers = self.killers.get(ply).copied().unwrap_or_default();
+        let moves = moves
+            .into_iter()
+            .map(|mv| {
+                let score = if hash_move == Some(mv) {
+                    HASH_MOVE_SCORE
+                } else if mv.capture && game.see_ge(&mv, 0) {
+                    CAPTURE_SCORE + mvv_lva(game, &mv)
+                } else if mv.capture {
+                    BAD_CAPTURE_SCORE + mvv_lva(game, &mv)
+                } else if let Some(promote) = mv.promote {
+                    PROMOTION_SCORE + promote.piece_type.value()
+                } else if let Some(slot) = killers.iter().position(|killer| *killer == Some(mv)) {
+                    KILLER_SCORE - slot as i32
+                } else {
+                    self.history[history_index(game.get_curr_player(), &mv)]
+                };
+                (mv, score)
+            })
+            .collect();
+        MovePicker { moves }
+    }
+
+    /// Records a beta cutoff by the `index`th move searched at a node of `depth`
+    pub fn on_cutoff<B: Board>(
+        &mut self,
+        game: &B,
+        game_move: &GameMove1d,
+        ply: usize,
+        depth: usize,
+        index: usize,
+    ) {
+        self.stats.cutoffs += 1;
+        if index == 0 {
+            self.stats.first_move_cutoffs += 1;
+        }
+        if !is_quiet(game_move) {
+            return;
+        }
+
+        if self.killers.len() <= ply {
+            self.killers.resize(ply + 1, [None; KILLERS_PER_PLY]);
+        }
+        let killers = &mut self.killers[ply];
+        if killers[0] != Some(*game_move) {
+            killers.rotate_right(1);
+            killers[0] = Some(*game_move);
+        }
+
+        // Deeper cutoffs save more work and get a larger bonus
+        let index = history_index(game.get_curr_player(), game_move);
+        let bonus = (depth * depth).min(HISTORY_LIMIT as usize) as i32;
+        self.history[index] += bonus;
+        if self.history[index] > HISTORY_LIMIT {
+            self.history.iter_mut().for_each(|score| *score /= 2);
+        }
+    }
+}
+
+/// Hands out moves best first. Selecting one move at a time avoids sorting the moves
+/// after a cutoff, which usually comes early.
+pub struct MovePicker {
+    moves: Vec<(GameMove1d, i32)>,
+}
+
+impl Iterator for MovePicker {
+    type Item = GameMove1d;
+
+    fn next(&mut self) -> Option<GameMove1d> {
+        // Searching backwards keeps generation order between moves of equal score
+        let (best, _) = self
+            .moves
+            .iter()
+            .enumerate()
+            .rev()
+            .max_by_key(|(_, (_, score))| *score)?;
+        Some(self.moves.remove(best).0)
+    }
+}
+
+pub(crate) fn captured_value<B: Board>(game: &B, game_move: &GameMove1d) -> i32 {
+    match game_move.passant {
+        Some(PassantTypes::PassantCapture(_)) => PieceTypes::Pawn.value(),
+        _ => game.piece_at(game_move.end).piece_type.value(),
+    }
+}
+
+fn is_quiet(game_move: &GameMove1d) -> bool {
+    !game_move.capture && game_move.promote.is_none()
+}
+
+// Most valuable victim first, then least valuable attacker. The king only counts as a
+// queen and a bit so the victim always decides first.
+fn mvv_lva<B: Board>(game: &B, game_move: &GameMove1d) -> i32 {
+    let promotion = game_move
+        .promote
+        .map_or(0, |piece| piece.piece_type.value());
+    let attacker = game
+        .piece_at(game_move.start)
+        .piece_type
+        .value()
+        .min(PieceTypes::Queen.value() + 1);
+    (captured_value(game, game_move) + promotion) * 1000 - attacker
+}
+
+fn history_index(side: PieceColors, game_move: &GameMove1d) -> usize {
+    let side = match side {
+        PieceColors::Black => 1,
+        _ => 0,
+    };
+    (side * BOARD_SIZE + game_move.start.value) * BOARD_SIZE + game_move.end.value
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::board::mailbox::Mailbox;
+
+    fn moves(game: &Mailbox, uci: &[&str]) -> Vec<GameMove1d> {
+        uci.iter()
+            .map(|mv| {
+                let parsed = GameMove1d::from_str(mv).unwrap();
+                *game
+                    .get_valid_moves()
+                    .iter()
+                    .find(|valid| valid.start == parsed.start && valid.end == parsed.end)
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_captures_by_mvv_lva() {
+        // The rook can be taken by the queen or the pawn, the knight only by the pawn
+        let game = Mailbox::setup_board(Some("4k3/8/2r1n3/3P4/Q7/8/8/4K3 w - - 0 1")).unwrap();
+        let ordered: Vec<GameMove1d> = MoveOrdering::default()
+            .picker(&game, game.get_valid_moves(), None, 0)
+            .take(3)
+            .collect();
+        assert_eq!(ordered, moves(&game, &["d5c6", "a4c6", "d5e6"]));
+
+        // Taking a defended pawn with the queen is tried after every quiet move
+        let game = Mailbox::setup_board(Some("4k3/8/2p5/3p4/8/8/8/3QK3 w - - 0 1")).unwrap();
+        let ordered: Vec<GameMove1d> = MoveOrdering::default()
+            .picker(&game, game.get_valid_moves(), None, 0)
+            .collect();
+        assert_eq!(ordered.last(), moves(&game, &["d1d5"]).first());
+    }
+
+    #[test]
+    fn test_hash_move_killers_and_history() {
+        let game = Mailbox::setup_board(None).unwrap();
+        let [hash_move, killer, history] = moves(&game, &["a2a3", "g1f3", "e2e4"])[..] else {
+            unreachable!()
+        };
+        let mut ordering = MoveOrdering::default();
+        ordering.on_cutoff(&game, &killer, 3, 2, 0);
+        ordering.on_cutoff(&game, &history, 5, 4, 1);
+        assert_eq!(ordering.stats.cutoffs, 2);
+        assert_eq!(ordering.stats.first_move_rate(), 0.5);
+
+        let ordered: Vec<GameMove1d> = ordering
+            .picker(&game, game.get_valid_moves(), Some(hash_move), 3)
+            .collect();
+        assert_eq!(ordered.len(), 20);
+        assert_eq!(ordered[..3], [hash_move, killer, history]);
+
+        // Killers are kept per ply, elsewhere only the history of both moves counts
+        let ordered: Vec<GameMove1d> = ordering
+            .picker(&game, game.get_valid_moves(), None, 4)
+            .take(2)
+            .collect();
+        assert_eq!(ordered, [history, killer]);
+    }
+}
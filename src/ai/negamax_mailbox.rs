@@ -1,234 +1,1056 @@
-#![allow(dead_code)]
-use std::sync::mpsc::Receiver;
+use std::cmp::Reverse;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
 use std::time::{Duration, Instant};
 
-use crate::board::mailbox::Mailbox;
-use crate::utils::checks::Checks;
+use lazy_static::lazy_static;
+
+use crate::ai::engine_options::{EngineOptions, OptionAction, Pruning};
+use crate::ai::mate_search::MateSearch;
+use crate::ai::move_ordering::{captured_value, MoveOrdering, OrderingStats};
+use crate::ai::score::{Score, MAX_PLY};
+use crate::ai::search_control::{SearchControl, SearchLimits};
+use crate::ai::transposition::{Bound, TranspositionTable};
+use crate::board::Board;
+use crate::utils::chess_errors::ChessError;
 use crate::utils::gamemove1d::GameMove1d;
 use crate::utils::piece_squares::piece_square_value;
 use crate::utils::pieces::{PieceColors, PieceTypes, Pieces};
 use crate::utils::position::Position;
 
-pub struct MailboxNegamax;
+// How many nodes are searched between checks of the search control
+const POLL_INTERVAL: usize = 1024;
+
+// Safety margin used when delta pruning captures in quiescence search
+const DELTA_MARGIN: i32 = 200;
+
+// Null move searches are reduced by this many plies, and more at higher depths
+const NULL_MOVE_REDUCTION: usize = 2;
+const NULL_MOVE_MIN_DEPTH: usize = 3;
+
+// Late moves are only reduced once the first few moves, usually the hash move, the good
+// captures and the killers, have been searched to full depth
+const LMR_MIN_DEPTH: usize = 3;
+const LMR_MIN_MOVES: usize = 3;
+const LMR_TABLE_SIZE: usize = 64;
+
+// Margins in centipawns per remaining ply for the shallow depth pruning
+const REVERSE_FUTILITY_DEPTH: usize = 3;
+const REVERSE_FUTILITY_MARGIN: i32 = 120;
+const FUTILITY_DEPTH: usize = 2;
+const FUTILITY_MARGIN: i32 = 150;
+
+// Singular extensions need a hash entry searched nearly as deep as the node, and every
+// other move has to fail low by this many centipawns per ply of depth
+const SINGULAR_MIN_DEPTH: usize = 6;
+const SINGULAR_DEPTH_MARGIN: usize = 3;
+const SINGULAR_MARGIN: i32 = 2;
+
+// Iterations from this depth on start with a window of this many centipawns on either
+// side of the previous score, doubled every time the score falls outside of it
+const ASPIRATION_MIN_DEPTH: usize = 4;
+const ASPIRATION_WINDOW: i32 = 25;
+
+// Win/draw/loss model parameters, an advantage of WDL_OFFSET centipawns wins half the time
+const WDL_OFFSET: f64 = 200.0;
+const WDL_SCALE: f64 = 100.0;
+
+lazy_static! {
+    // Reductions grow with the logarithm of both the remaining depth and the move number
+    static ref LMR_TABLE: [[usize; LMR_TABLE_SIZE]; LMR_TABLE_SIZE] = {
+        let mut table = [[0; LMR_TABLE_SIZE]; LMR_TABLE_SIZE];
+        for (depth, row) in table.iter_mut().enumerate().skip(1) {
+            for (index, reduction) in row.iter_mut().enumerate().skip(1) {
+                *reduction = (0.75 + (depth as f64).ln() * (index as f64).ln() / 2.25) as usize;
+            }
+        }
+        table
+    };
+}
+
+// Engine state that outlives a single search. Cloning is cheap and shares the hash table,
+// so a clone can be moved into the search thread.
+#[derive(Clone)]
+pub struct MailboxNegamax {
+    pub options: EngineOptions,
+    tt: Arc<TranspositionTable>,
+}
+
+impl Default for MailboxNegamax {
+    fn default() -> Self {
+        let options = EngineOptions::default();
+        MailboxNegamax {
+            tt: Arc::new(TranspositionTable::new(options.hash_mb)),
+            options,
+        }
+    }
+}
 
 impl MailboxNegamax {
-    pub fn uci_infinite_find_move(
-        game: Mailbox,
-        rx: Receiver<&str>,
+    pub fn set_option(&mut self, name: &str, value: Option<&str>) -> Result<(), ChessError> {
+        match self.options.set(name, value)? {
+            OptionAction::ResizeHash(size_mb) => {
+                self.tt = Arc::new(TranspositionTable::new(size_mb));
+            }
+            OptionAction::ClearHash => self.tt.clear(),
+            OptionAction::None => {}
+        }
+        Ok(())
+    }
+
+    pub fn new_game(&self) {
+        self.tt.clear();
+    }
+
+    // Lazy SMP: helper threads run their own iterative deepening on the shared hash table
+    // and only help by filling it, the main thread decides when to stop and what to play
+    pub fn uci_find_move<B: Board>(
+        &self,
+        game: B,
         available_moves: Option<Vec<GameMove1d>>,
+        control: &SearchControl,
     ) -> GameMove1d {
         let start_time = Instant::now();
-        let mut best_move: GameMove1d;
-        let mut best_score: i32;
-        let mut depth: usize = 1;
-
-        (best_move, best_score) = root_nega_max(&game, depth, available_moves.clone());
-        println!(
-            "info depth {} pv {} score cp {} time {}",
-            depth,
-            best_move,
-            best_score,
-            start_time.elapsed().as_millis(),
-        );
-        depth += 1;
+        let limits = control.limits;
+        let max_plies = limits.max_plies.unwrap_or(MAX_PLY as usize);
+        let helper_nodes = AtomicUsize::new(0);
+        let root_moves = available_moves.unwrap_or_else(|| game.get_valid_moves());
+        if root_moves.is_empty() {
+            control.wait_until_released();
+            println!("bestmove 0000");
+            return GameMove1d::default();
+        }
+        self.tt.new_search();
 
-        loop {
-            match rx.try_recv() {
-                Ok("stop") => break,
-                Ok(_) => {}
-                Err(std::sync::mpsc::TryRecvError::Empty) => {}
-                Err(std::sync::mpsc::TryRecvError::Disconnected) => break,
+        let lines = thread::scope(|scope| {
+            for helper in 1..self.search_threads(&limits) {
+                let (game, root_moves, helper_nodes) = (&game, root_moves.clone(), &helper_nodes);
+                scope.spawn(move || {
+                    self.helper_search(game, helper, root_moves, max_plies, control, helper_nodes)
+                });
             }
 
-            (best_move, best_score) = root_nega_max(&game, depth, available_moves.clone());
-            println!(
-                "info depth {} pv {} score cp {} time {}",
-                depth,
-                best_move,
-                best_score,
-                start_time.elapsed().as_millis(),
-            );
+            let mut searcher = Searcher::new(&self.tt, limits.max_nodes)
+                .with_control(control)
+                .with_pruning(self.options.pruning);
+            let mut time = limits.time;
+            let mut lines: Vec<SearchLine> = Vec::new();
+            let mut elapsed_time = Duration::from_millis(1);
+            let mut depth: usize = 1;
+
+            loop {
+                let iteration_lines = searcher.aspiration_lines(
+                    &game,
+                    depth,
+                    &root_moves,
+                    self.options.multi_pv,
+                    lines.first().map(|line| line.score),
+                    |searcher, failed| {
+                        self.print_info(depth, failed, &start_time, searcher, &helper_nodes)
+                    },
+                );
+                // An interrupted iteration still returns the best of the root moves it
+                // finished, which always include the previous best move since that is
+                // searched first. Only a window that failed low says nothing about it.
+                if iteration_lines
+                    .first()
+                    .is_some_and(|line| line.bound != Bound::Upper)
+                {
+                    lines = iteration_lines;
+                    self.print_info(depth, &lines, &start_time, &searcher, &helper_nodes);
+                    time.on_iteration(lines[0].best_move, lines[0].score);
+                }
+                if searcher.aborted {
+                    break;
+                }
+                depth += 1;
+
+                // The next iteration is expected to grow the total search time by the same
+                // ratio as the last one did
+                let last_elapsed_time = elapsed_time;
+                elapsed_time = start_time.elapsed();
+                let elapsed_ratio = elapsed_time.as_nanos() / last_elapsed_time.as_nanos().max(1);
+                let predicted_iteration = (elapsed_time.as_millis() * elapsed_ratio)
+                    .saturating_sub(elapsed_time.as_millis());
+                if depth > max_plies
+                    || control.is_stopped()
+                    || (!control.is_pondering()
+                        && !time.should_start_iteration(control.elapsed(), predicted_iteration))
+                {
+                    break;
+                }
+            }
+
+            // Report the final node count when the last iteration was cut short
+            if searcher.aborted {
+                let nodes = searcher.nodes + helper_nodes.load(Ordering::Relaxed);
+                let elapsed = start_time.elapsed().as_millis();
+                println!(
+                    "info nodes {} nps {} time {}",
+                    nodes,
+                    nodes as u128 * 1000 / elapsed.max(1),
+                    elapsed
+                );
+            }
+
+            // A search that ran out of work may still have to wait for the GUI, after
+            // which the helpers are stopped as well
+            control.wait_until_released();
+            control.stop();
+            lines
+        });
+
+        let (best_move, ponder_move) = match lines.first() {
+            Some(line) => (line.best_move, line.pv.get(1).copied()),
+            // Aborted before a single root move was searched to the end
+            None => (root_moves[0], None),
+        };
+        match ponder_move {
+            Some(ponder_move) => println!("bestmove {} ponder {}", best_move, ponder_move),
+            None => println!("bestmove {}", best_move),
+        }
+        best_move
+    }
+
+    // Node limited searches run on the main thread alone, helpers would search past the
+    // limit and make the result depend on thread timing
+    fn search_threads(&self, limits: &SearchLimits) -> usize {
+        match limits.max_nodes {
+            Some(_) => 1,
+            None => self.options.threads,
+        }
+    }
+
+    // Helpers start at staggered depths so that they do not all search the same tree in
+    // lockstep with the main thread, and stop at the main thread's depth limit
+    fn helper_search<B: Board>(
+        &self,
+        game: &B,
+        helper: usize,
+        root_moves: Vec<GameMove1d>,
+        max_plies: usize,
+        control: &SearchControl,
+        helper_nodes: &AtomicUsize,
+    ) {
+        let mut searcher = Searcher::new(&self.tt, None)
+            .with_control(control)
+            .with_pruning(self.options.pruning);
+        let mut depth = 1 + helper % 2;
+        let mut reported = 0;
+        while !searcher.aborted && depth <= max_plies {
+            searcher.root_nega_max(game, depth, Some(root_moves.clone()));
+            helper_nodes.fetch_add(searcher.nodes - reported, Ordering::Relaxed);
+            reported = searcher.nodes;
             depth += 1;
         }
+    }
 
-        println!("bestmove {}", best_move);
-        best_move
+    /// Searches `game` to a fixed depth and returns the best `multi_pv` lines, best first
+    pub fn analyse<B: Board>(
+        &self,
+        game: &B,
+        max_plies: usize,
+        multi_pv: usize,
+    ) -> Vec<SearchLine> {
+        self.analyse_with_stats(game, max_plies, multi_pv).0
     }
-    pub fn uci_find_move(
-        game: Mailbox,
-        search_time: u128,
+
+    /// Like `analyse`, but also reports how well the moves were ordered
+    pub fn analyse_with_stats<B: Board>(
+        &self,
+        game: &B,
+        max_plies: usize,
+        multi_pv: usize,
+    ) -> (Vec<SearchLine>, OrderingStats) {
+        let mut searcher = Searcher::new(&self.tt, None).with_pruning(self.options.pruning);
+        self.tt.new_search();
+        let mut lines = Vec::new();
+        for depth in 1..=max_plies {
+            lines = searcher.root_lines(game, depth, None, multi_pv, full_window());
+        }
+        (lines, searcher.ordering.stats)
+    }
+
+    pub fn uci_search_mate<B: Board>(
+        &self,
+        game: B,
         available_moves: Option<Vec<GameMove1d>>,
-        max_plies: Option<usize>,
-        _max_nodes: Option<usize>,
-        rx: Receiver<&str>,
+        mate_moves: usize,
+        control: &SearchControl,
     ) -> GameMove1d {
         let start_time = Instant::now();
-        let mut elapsed_time;
-        let mut last_elapsed_time;
-        let mut elapsed_ratio;
-        let mut best_move: GameMove1d;
-        let mut best_score: i32;
-        let mut depth: usize = 1;
-
-        (best_move, best_score) = root_nega_max(&game, depth, available_moves.clone());
-        println!(
-            "info depth {} pv {} score cp {} time {} ",
-            depth,
-            best_move,
-            best_score,
-            start_time.elapsed().as_millis(),
-        );
-        depth += 1;
-        last_elapsed_time = Duration::from_millis(1);
-        elapsed_time = start_time.elapsed();
-        elapsed_ratio = elapsed_time.as_nanos() / last_elapsed_time.as_nanos();
-
-        while elapsed_time.as_millis() * elapsed_ratio < search_time
-            && depth < max_plies.unwrap_or(usize::MAX)
-        {
-            match rx.try_recv() {
-                Ok("stop") => break,
-                Ok(_) => {}
-                Err(std::sync::mpsc::TryRecvError::Empty) => {}
-                Err(std::sync::mpsc::TryRecvError::Disconnected) => break,
+        let root_moves = available_moves.unwrap_or_else(|| game.get_valid_moves());
+        if root_moves.is_empty() {
+            control.wait_until_released();
+            println!("bestmove 0000");
+            return GameMove1d::default();
+        }
+        let mut search = MateSearch::new(Some(control));
+        let mate = search.find_mate(&game, mate_moves, Some(root_moves.clone()));
+        control.wait_until_released();
+        let elapsed = start_time.elapsed().as_millis();
+        let nps = search.nodes as u128 * 1000 / elapsed.max(1);
+
+        let best_move = match mate {
+            Some((moves, pv)) => {
+                let line: Vec<String> = pv.iter().map(|mv| mv.to_string()).collect();
+                println!(
+                    "info depth {} score {} nodes {} nps {} time {} pv {}",
+                    2 * moves - 1,
+                    Score::mate_in(2 * moves - 1),
+                    search.nodes,
+                    nps,
+                    elapsed,
+                    line.join(" ")
+                );
+                pv[0]
             }
+            None => {
+                // Still answer with a legal move so the GUI is not left waiting
+                println!(
+                    "info string no mate in {} found nodes {} time {}",
+                    mate_moves, search.nodes, elapsed
+                );
+                let mut searcher = Searcher::new(&self.tt, None);
+                searcher.root_nega_max(&game, 1, Some(root_moves)).0
+            }
+        };
+
+        println!("bestmove {}", best_move);
+        best_move
+    }
 
-            (best_move, best_score) = root_nega_max(&game, depth, available_moves.clone());
+    fn print_info(
+        &self,
+        depth: usize,
+        lines: &[SearchLine],
+        start_time: &Instant,
+        searcher: &Searcher,
+        helper_nodes: &AtomicUsize,
+    ) {
+        let elapsed = start_time.elapsed().as_millis();
+        let nodes = searcher.nodes + helper_nodes.load(Ordering::Relaxed);
+        for (index, line) in lines.iter().enumerate() {
+            let wdl = if self.options.show_wdl {
+                let (win, draw, loss) = win_draw_loss(line.score);
+                format!(" wdl {win} {draw} {loss}")
+            } else {
+                String::new()
+            };
+            let bound = match line.bound {
+                Bound::Exact => "",
+                Bound::Lower => " lowerbound",
+                Bound::Upper => " upperbound",
+            };
+            let pv: Vec<String> = line.pv.iter().map(|mv| mv.to_string()).collect();
             println!(
-                "info depth {} pv {} score cp {} time {} ",
+                "info multipv {} depth {} seldepth {} score {}{}{} nodes {} nps {} hashfull {} time {} pv {}",
+                index + 1,
                 depth,
-                best_move,
-                best_score,
-                start_time.elapsed().as_millis(),
+                searcher.seldepth,
+                line.score,
+                bound,
+                wdl,
+                nodes,
+                nodes as u128 * 1000 / elapsed.max(1),
+                self.tt.hashfull(),
+                elapsed,
+                pv.join(" "),
             );
-            depth += 1;
-            last_elapsed_time = elapsed_time;
-            elapsed_time = start_time.elapsed();
-            elapsed_ratio = elapsed_time.as_nanos() / last_elapsed_time.as_nanos();
         }
-
-        println!("bestmove {}", best_move);
-        best_move
     }
-    pub fn uci_search_mate(
-        game: Mailbox,
-        search_time: u128,
-        available_moves: Option<Vec<GameMove1d>>,
-        mate_moves: usize,
-    ) -> GameMove1d {
-        // TODO: set up search thread, and listen for both stop command and search information
-        // If stop command is issued, immediately stop search and return most recent best move
-        let best_move: GameMove1d = Default::default();
+}
 
-        // TODO: if search ends normally, return best move
-        best_move
+// Rough logistic model of the expected game outcome, in per mille, for a centipawn score
+fn win_draw_loss(score: Score) -> (u32, u32, u32) {
+    match score.mate_moves() {
+        Some(moves) if moves > 0 => return (1000, 0, 0),
+        Some(_) => return (0, 0, 1000),
+        None => {}
     }
+    let score = score.value() as f64;
+    let win = (1000.0 / (1.0 + ((WDL_OFFSET - score) / WDL_SCALE).exp())).round() as u32;
+    let loss = (1000.0 / (1.0 + ((WDL_OFFSET + score) / WDL_SCALE).exp())).round() as u32;
+    (win, 1000 - win - loss, loss)
 }
 
-fn root_nega_max(
-    game: &Mailbox,
-    depth: usize,
-    available_moves: Option<Vec<GameMove1d>>,
-) -> (GameMove1d, i32) {
-    let valid_moves = available_moves.unwrap_or_else(|| game.get_valid_moves());
-    let mut max_score = i32::MIN;
-    let mut best_move = valid_moves[0];
-    for mv in valid_moves {
-        let score = nega_max(game.make_move(&mv), depth - 1);
-        if max_score < score {
-            max_score = score;
-            best_move = mv;
-        }
-    }
-    (best_move, max_score)
+/// One of the best lines found by a search, scored from the side to move's point of view
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchLine {
+    pub best_move: GameMove1d,
+    pub score: Score,
+    // Whether the score is exact or only a bound, when the search window was too narrow
+    pub bound: Bound,
+    pub pv: Vec<GameMove1d>,
 }
 
-fn nega_max(game: Mailbox, depth: usize) -> i32 {
-    let valid_moves = game.get_valid_moves();
-    if depth == 0 {
-        return evaluate(game, valid_moves);
+// State shared by every node of a single search
+struct Searcher<'a> {
+    tt: &'a TranspositionTable,
+    nodes: usize,
+    max_nodes: Option<usize>,
+    // Polled every few nodes for stop commands and the hard time limit
+    control: Option<&'a SearchControl>,
+    aborted: bool,
+    // Deepest ply reached in the current iteration, including quiescence
+    seldepth: usize,
+    // Triangular table, the line at index `ply` is the best continuation found from the
+    // node at that ply, so index 0 holds the principal variation from the root
+    pv_table: Vec<Vec<GameMove1d>>,
+    ordering: MoveOrdering,
+    pruning: Pruning,
+    // Set right before searching the reply to a null move, so that no two null moves
+    // are ever made in a row
+    after_null: bool,
+    extend: bool,
+    // Extensions on a line are capped by the depth of the current iteration
+    root_depth: usize,
+    line: Vec<LineState>,
+    // Move left out by the next node searched, used to test the hash move for singularity
+    excluded_move: Option<GameMove1d>,
+}
+
+// What the line from the root to a node did so far
+#[derive(Clone, Copy, Debug, Default)]
+struct LineState {
+    extensions: usize,
+    // Square of the capture that led to the node, a capture back on it is a recapture
+    capture_square: Option<Position>,
+}
+
+impl<'a> Searcher<'a> {
+    fn new(tt: &'a TranspositionTable, max_nodes: Option<usize>) -> Self {
+        Searcher {
+            tt,
+            nodes: 0,
+            max_nodes,
+            control: None,
+            aborted: false,
+            seldepth: 0,
+            pv_table: Vec::new(),
+            ordering: MoveOrdering::default(),
+            pruning: Pruning::ALL,
+            after_null: false,
+            extend: true,
+            root_depth: 0,
+            line: Vec::new(),
+            excluded_move: None,
+        }
+    }
+
+    fn with_control(mut self, control: &'a SearchControl) -> Self {
+        self.control = Some(control);
+        self
+    }
+
+    fn with_pruning(mut self, pruning: Pruning) -> Self {
+        self.pruning = pruning;
+        self
     }
-    let mut max = i32::MIN + 1;
-    for game_move in valid_moves {
-        let new_game = game.make_move(&game_move);
-        let score = -nega_max(new_game, depth - 1);
-        if score > max {
-            max = score;
+
+    // Counts a node, or aborts the search once the node limit has been reached so that
+    // exactly `max_nodes` nodes are ever visited
+    fn visit_node(&mut self, ply: usize) -> bool {
+        if self.aborted {
+            return false;
+        }
+        if self.nodes.is_multiple_of(POLL_INTERVAL)
+            && self.control.is_some_and(SearchControl::should_abort)
+        {
+            self.aborted = true;
+            return false;
+        }
+        if self
+            .max_nodes
+            .is_some_and(|max_nodes| self.nodes >= max_nodes)
+        {
+            self.aborted = true;
+            return false;
         }
+        self.nodes += 1;
+        self.seldepth = self.seldepth.max(ply);
+        true
     }
-    max
-}
 
-fn is_repetition(game1: &Mailbox, game2: &Mailbox) -> bool {
-    let mut is_rep = true;
-    if game1.board != game2.board {
-        is_rep = false;
+    fn line_state(&self, ply: usize) -> LineState {
+        self.line.get(ply).copied().unwrap_or_default()
     }
-    if game1.get_castle_rights() != game2.get_castle_rights() {
-        is_rep = false;
+
+    // Records how the node after `ply` was reached, `None` being a null move
+    fn enter_child(&mut self, ply: usize, game_move: Option<&GameMove1d>, extension: usize) {
+        if self.line.len() <= ply + 1 {
+            self.line.resize(ply + 2, LineState::default());
+        }
+        self.line[ply + 1] = LineState {
+            extensions: self.line_state(ply).extensions + extension,
+            capture_square: game_move.filter(|mv| mv.capture).map(|mv| mv.end),
+        };
     }
-    if game1.en_passant != game2.en_passant {
-        is_rep = false;
+
+    // Checks, forced moves and recaptures on the principal variation are searched one ply
+    // deeper, as long as the line has not been extended by the iteration depth already
+    fn extension<B: Board>(
+        &self,
+        ply: usize,
+        game_move: &GameMove1d,
+        new_game: &B,
+        pv_node: bool,
+        forced: bool,
+    ) -> usize {
+        let line = self.line_state(ply);
+        if !self.extend || line.extensions >= self.root_depth {
+            return 0;
+        }
+        let check = new_game.get_check().is_some();
+        let recapture = pv_node && game_move.capture && line.capture_square == Some(game_move.end);
+        usize::from(check || forced || recapture)
     }
-    if game1.get_curr_player() != game2.get_curr_player() {
-        is_rep = false;
+
+    // The hash move is singular when every other move fails low against a margin below its
+    // stored score, searched at half the depth
+    fn is_singular<B: Board>(
+        &mut self,
+        game: &mut B,
+        hash_move: GameMove1d,
+        depth: usize,
+        ply: usize,
+        tt_score: Score,
+    ) -> bool {
+        let singular_beta = tt_score - SINGULAR_MARGIN * depth as i32;
+        self.excluded_move = Some(hash_move);
+        let score = self.nega_max(game, (depth - 1) / 2, ply, singular_beta - 1, singular_beta);
+        score < singular_beta
     }
-    is_rep
-}
 
-fn is_draw(game: &Mailbox) -> bool {
-    let mut is_draw = false;
+    fn principal_variation(&self) -> &[GameMove1d] {
+        self.pv_table.first().map_or(&[], Vec::as_slice)
+    }
 
-    // 3-fold repetition
-    if game.full_moves > 4 {
-        let ply2_back = game.get_prev().unwrap().get_prev().unwrap();
-        let ply4_back = ply2_back.get_prev().unwrap().get_prev().unwrap();
-        if is_repetition(game, &ply2_back) && is_repetition(game, &ply4_back) {
-            is_draw = true;
+    fn clear_pv(&mut self, ply: usize) {
+        if self.pv_table.len() <= ply {
+            self.pv_table.resize_with(ply + 1, Vec::new);
         }
+        self.pv_table[ply].clear();
     }
 
-    // 50-move rule
-    if game.half_moves >= 100 {
-        is_draw = true;
+    // Makes `game_move` followed by the child's line the best line at `ply`
+    fn update_pv(&mut self, ply: usize, game_move: GameMove1d) {
+        if self.pv_table.len() <= ply + 1 {
+            self.pv_table.resize_with(ply + 2, Vec::new);
+        }
+        let (parent, child) = self.pv_table.split_at_mut(ply + 1);
+        parent[ply].clear();
+        parent[ply].push(game_move);
+        parent[ply].extend_from_slice(&child[0]);
     }
 
-    is_draw
-}
+    // Finds the best `multi_pv` root moves by searching the root again with every move
+    // found so far excluded. An aborted search keeps only a partial first line, so there
+    // is always a move to play.
+    // The first line is searched with `window`, the others with a full window
+    fn root_lines<B: Board>(
+        &mut self,
+        game: &B,
+        depth: usize,
+        available_moves: Option<Vec<GameMove1d>>,
+        multi_pv: usize,
+        window: (Score, Score),
+    ) -> Vec<SearchLine> {
+        let mut root_moves = available_moves.unwrap_or_else(|| game.get_valid_moves());
+        let mut lines: Vec<SearchLine> = Vec::new();
+        self.seldepth = 0;
+        while lines.len() < multi_pv.max(1) && !root_moves.is_empty() {
+            // Only the full root search may store its result in the hash table
+            let (alpha, beta) = if lines.is_empty() {
+                window
+            } else {
+                full_window()
+            };
+            let (best_move, score) = self.search_root(
+                game,
+                depth,
+                root_moves.clone(),
+                lines.is_empty(),
+                (alpha, beta),
+            );
+            // Keep partial results only if at least one root move was fully searched
+            if self.aborted && (!lines.is_empty() || score == -Score::INFINITE) {
+                break;
+            }
+            lines.push(SearchLine {
+                best_move,
+                score,
+                bound: bound_of(score, alpha, beta),
+                pv: self.principal_variation().to_vec(),
+            });
+            if self.aborted {
+                break;
+            }
+            root_moves.retain(|mv| *mv != best_move);
+        }
+        lines.sort_by_key(|line| Reverse(line.score));
+        lines
+    }
 
-fn evaluate(game: Mailbox, valid_moves: Vec<GameMove1d>) -> i32 {
-    // Check if game is terminal
-    if valid_moves.is_empty() {
-        let check = game.get_check();
-        match check {
-            Some(Checks::White) => {
-                if game.get_curr_player() == PieceColors::Black {
-                    return i32::MAX;
-                } else if game.get_curr_player() == PieceColors::White {
-                    return i32::MIN + 1;
+    // Searches the root with a narrow window around the score of the previous iteration and
+    // widens the side that failed until the score lands inside. Every failed attempt is
+    // handed to `on_fail` before searching again.
+    fn aspiration_lines<B: Board>(
+        &mut self,
+        game: &B,
+        depth: usize,
+        root_moves: &[GameMove1d],
+        multi_pv: usize,
+        previous: Option<Score>,
+        mut on_fail: impl FnMut(&Self, &[SearchLine]),
+    ) -> Vec<SearchLine> {
+        let mut delta = ASPIRATION_WINDOW;
+        // Other lines are searched with a full window anyway, so they gain nothing from it
+        let (mut alpha, mut beta) = match previous {
+            Some(score) if depth >= ASPIRATION_MIN_DEPTH && multi_pv <= 1 && !score.is_mate() => {
+                (score - delta, score + delta)
+            }
+            _ => full_window(),
+        };
+        loop {
+            let lines = self.root_lines(
+                game,
+                depth,
+                Some(root_moves.to_vec()),
+                multi_pv,
+                (alpha, beta),
+            );
+            let Some(line) = lines.first() else {
+                return lines;
+            };
+            if self.aborted || line.bound == Bound::Exact {
+                return lines;
+            }
+            on_fail(self, &lines);
+            if line.bound == Bound::Upper {
+                alpha = (line.score - delta).max(-Score::INFINITE);
+            } else {
+                beta = (line.score + delta).min(Score::INFINITE);
+            }
+            delta *= 2;
+        }
+    }
+
+    fn root_nega_max<B: Board>(
+        &mut self,
+        game: &B,
+        depth: usize,
+        available_moves: Option<Vec<GameMove1d>>,
+    ) -> (GameMove1d, Score) {
+        let valid_moves = available_moves.unwrap_or_else(|| game.get_valid_moves());
+        self.seldepth = 0;
+        self.search_root(game, depth, valid_moves, true, full_window())
+    }
+
+    // When the search is aborted the best move among the fully searched root moves is
+    // returned, which is the first move if none of them finished
+    fn search_root<B: Board>(
+        &mut self,
+        game: &B,
+        depth: usize,
+        valid_moves: Vec<GameMove1d>,
+        store: bool,
+        (mut alpha, beta): (Score, Score),
+    ) -> (GameMove1d, Score) {
+        let hash_move = self
+            .tt
+            .probe(game.get_key())
+            .and_then(|entry| entry.best_move(&valid_moves));
+        let valid_moves: Vec<GameMove1d> = self
+            .ordering
+            .picker(game, valid_moves, hash_move, 0)
+            .collect();
+        self.clear_pv(0);
+        self.root_depth = depth;
+        self.line.clear();
+
+        // The whole tree is searched by making and unmaking moves on a single board
+        let mut position = game.clone();
+        let original_alpha = alpha;
+        let mut max_score = -Score::INFINITE;
+        let mut best_move = valid_moves[0];
+        for (index, mv) in valid_moves.into_iter().enumerate() {
+            position.make_move_in_place(&mv);
+            let extension = self.extension(0, &mv, &position, true, false);
+            self.enter_child(0, Some(&mv), extension);
+            let score = self.pv_search(
+                &mut position,
+                depth - 1 + extension,
+                1,
+                alpha,
+                beta,
+                index == 0,
+            );
+            position.unmake_move();
+            if self.aborted {
+                break;
+            }
+            if max_score < score {
+                max_score = score;
+                best_move = mv;
+                self.update_pv(0, mv);
+                if score > alpha {
+                    alpha = score;
+                    if alpha >= beta {
+                        break;
+                    }
                 }
             }
-            Some(Checks::Black) => {
-                if game.get_curr_player() == PieceColors::Black {
-                    return i32::MIN + 1;
-                } else if game.get_curr_player() == PieceColors::White {
-                    return i32::MAX;
+        }
+        if store && !self.aborted {
+            self.tt.store(
+                game.get_key(),
+                depth,
+                0,
+                bound_of(max_score, original_alpha, beta),
+                max_score,
+                Some(&best_move),
+            );
+        }
+        (best_move, max_score)
+    }
+
+    // Fail-soft alpha-beta with principal variation search. Scores are relative to the
+    // side to move, with mates counted in plies from the root so negating them is exact.
+    fn nega_max<B: Board>(
+        &mut self,
+        game: &mut B,
+        depth: usize,
+        ply: usize,
+        mut alpha: Score,
+        beta: Score,
+    ) -> Score {
+        let after_null = std::mem::take(&mut self.after_null);
+        let excluded = self.excluded_move.take();
+        if depth == 0 {
+            return self.quiescence(game, ply, alpha, beta);
+        }
+        self.clear_pv(ply);
+        if !self.visit_node(ply) {
+            return Score::DRAW;
+        }
+        let mut valid_moves = game.get_valid_moves();
+        if valid_moves.is_empty() {
+            return evaluate(game, &valid_moves, ply);
+        }
+        let one_reply = valid_moves.len() == 1;
+        if let Some(excluded) = excluded {
+            valid_moves.retain(|mv| *mv != excluded);
+            if valid_moves.is_empty() {
+                return alpha;
+            }
+        }
+
+        // Only trust stored bounds outside of the principal variation. The entry describes
+        // every move, so it cannot answer a search that leaves one of them out.
+        let entry = self.tt.probe(game.get_key());
+        let mut hash_move = None;
+        if let Some(entry) = entry {
+            let score = entry.score(ply);
+            if alpha + 1 == beta
+                && excluded.is_none()
+                && entry.depth as usize >= depth
+                && !is_draw(game)
+            {
+                match entry.bound {
+                    Bound::Exact => return score,
+                    Bound::Lower if score >= beta => return score,
+                    Bound::Upper if score <= alpha => return score,
+                    _ => {}
+                }
+            }
+            hash_move = entry.best_move(&valid_moves);
+        }
+
+        // Selectivity is restricted to null window nodes, the principal variation is
+        // always searched in full
+        let in_check = game.get_check().is_some();
+        let pv_node = alpha + 1 != beta;
+        let static_eval = evaluate(game, &valid_moves, ply);
+        if !pv_node && !in_check && !beta.is_mate() && excluded.is_none() {
+            // Reverse futility: this far above beta a shallow search will not fall below it
+            if self.pruning.reverse_futility
+                && depth <= REVERSE_FUTILITY_DEPTH
+                && static_eval - REVERSE_FUTILITY_MARGIN * depth as i32 >= beta
+            {
+                return static_eval;
+            }
+
+            // Null move: if the position still fails high after passing the turn, a real
+            // move would do even better. Zugzwang makes passing the best option with only
+            // king and pawns left, so null moves are not tried there.
+            if self.pruning.null_move
+                && !after_null
+                && depth >= NULL_MOVE_MIN_DEPTH
+                && static_eval >= beta
+                && has_non_pawn_material(game)
+            {
+                let reduction = NULL_MOVE_REDUCTION + depth / 6;
+                self.enter_child(ply, None, 0);
+                self.after_null = true;
+                game.make_null_move_in_place();
+                let score = -self.nega_max(
+                    game,
+                    depth.saturating_sub(1 + reduction),
+                    ply + 1,
+                    -beta,
+                    -beta + 1,
+                );
+                game.unmake_move();
+                if self.aborted {
+                    return Score::DRAW;
+                }
+                if score >= beta {
+                    // A mate found after passing the turn is not proven
+                    return if score.is_mate() { beta } else { score };
+                }
+            }
+        }
+
+        let singular_move = match (entry, hash_move) {
+            (Some(entry), Some(hash_move))
+                if self.extend
+                    && excluded.is_none()
+                    && depth >= SINGULAR_MIN_DEPTH
+                    && entry.depth as usize + SINGULAR_DEPTH_MARGIN >= depth
+                    && entry.bound != Bound::Upper
+                    && !entry.score(ply).is_mate() =>
+            {
+                self.is_singular(game, hash_move, depth, ply, entry.score(ply))
+                    .then_some(hash_move)
+            }
+            _ => None,
+        };
+        if self.aborted {
+            return Score::DRAW;
+        }
+
+        // Futility: close to the leaves, a quiet move will not make up the distance to alpha
+        let futility_value = static_eval + FUTILITY_MARGIN * depth as i32;
+        let futility_pruning = self.pruning.futility
+            && !pv_node
+            && !in_check
+            && depth <= FUTILITY_DEPTH
+            && futility_value <= alpha
+            && !alpha.is_mate();
+
+        let original_alpha = alpha;
+        let mut max = -Score::INFINITE;
+        let mut best_move = None;
+        let picker = self.ordering.picker(game, valid_moves, hash_move, ply);
+        for (index, game_move) in picker.enumerate() {
+            game.make_move_in_place(&game_move);
+            let quiet =
+                !game_move.capture && game_move.promote.is_none() && game.get_check().is_none();
+            if futility_pruning && quiet && index > 0 {
+                game.unmake_move();
+                max = max.max(futility_value);
+                continue;
+            }
+            let forced = one_reply || singular_move == Some(game_move);
+            let extension = self.extension(ply, &game_move, game, pv_node, forced);
+            self.enter_child(ply, Some(&game_move), extension);
+            let new_depth = depth - 1 + extension;
+
+            // Late move reductions: quiet moves this far down the ordering rarely raise
+            // alpha, so they are searched shallower first and only re-searched if they do
+            let reduction = if self.pruning.late_move_reductions
+                && quiet
+                && !in_check
+                && depth >= LMR_MIN_DEPTH
+                && index >= LMR_MIN_MOVES
+            {
+                late_move_reduction(depth, index)
+                    .saturating_sub(usize::from(pv_node))
+                    .min(new_depth - 1)
+            } else {
+                0
+            };
+            let score = if reduction > 0 {
+                let reduced =
+                    -self.nega_max(game, new_depth - reduction, ply + 1, -alpha - 1, -alpha);
+                if reduced > alpha && !self.aborted {
+                    self.pv_search(game, new_depth, ply + 1, alpha, beta, false)
+                } else {
+                    reduced
+                }
+            } else {
+                self.pv_search(game, new_depth, ply + 1, alpha, beta, index == 0)
+            };
+            game.unmake_move();
+            if self.aborted {
+                return Score::DRAW;
+            }
+            if score > max {
+                max = score;
+                if score > alpha {
+                    alpha = score;
+                    best_move = Some(game_move);
+                    self.update_pv(ply, game_move);
+                    if alpha >= beta {
+                        self.ordering.on_cutoff(game, &game_move, ply, depth, index);
+                        break;
+                    }
+                }
+            }
+        }
+
+        if excluded.is_some() {
+            return max;
+        }
+        let bound = bound_of(max, original_alpha, beta);
+        self.tt
+            .store(game.get_key(), depth, ply, bound, max, best_move.as_ref());
+        max
+    }
+
+    // Searches a child position from the parent's point of view. The first move gets the
+    // full window, every later move is first tried with a null window around alpha and
+    // only re-searched with the full window if it unexpectedly lands inside it.
+    fn pv_search<B: Board>(
+        &mut self,
+        game: &mut B,
+        depth: usize,
+        ply: usize,
+        alpha: Score,
+        beta: Score,
+        first_move: bool,
+    ) -> Score {
+        if first_move {
+            return -self.nega_max(game, depth, ply, -beta, -alpha);
+        }
+        let score = -self.nega_max(game, depth, ply, -alpha - 1, -alpha);
+        if score > alpha && score < beta && !self.aborted {
+            -self.nega_max(game, depth, ply, -beta, -alpha)
+        } else {
+            score
+        }
+    }
+
+    // Resolves captures and promotions at the leaves so that a position is never scored in
+    // the middle of an exchange. When the side to move is in check every evasion is searched
+    // instead, since standing pat is not an option.
+    fn quiescence<B: Board>(
+        &mut self,
+        game: &mut B,
+        ply: usize,
+        mut alpha: Score,
+        beta: Score,
+    ) -> Score {
+        // Captures resolved here are not part of the reported line
+        self.clear_pv(ply);
+        if !self.visit_node(ply) {
+            return Score::DRAW;
+        }
+        let valid_moves = game.get_valid_moves();
+        if valid_moves.is_empty() || is_draw(game) {
+            return evaluate(game, &valid_moves, ply);
+        }
+
+        let in_check = game.get_check().is_some();
+        let mut max = -Score::INFINITE;
+        let stand_pat = evaluate(game, &valid_moves, ply);
+        if !in_check {
+            if stand_pat >= beta {
+                return stand_pat;
+            }
+            // Even winning a queen would not bring the score back up to alpha
+            if stand_pat + PieceTypes::Queen.value() + DELTA_MARGIN < alpha {
+                return stand_pat;
+            }
+            max = stand_pat;
+            if stand_pat > alpha {
+                alpha = stand_pat;
+            }
+        }
+
+        for game_move in self.ordering.picker(game, valid_moves, None, ply) {
+            if !in_check {
+                if !game_move.capture && game_move.promote.is_none() {
+                    continue;
+                }
+                // Delta pruning: skip captures that cannot raise the score to alpha
+                if game_move.promote.is_none()
+                    && stand_pat + captured_value(game, &game_move) + DELTA_MARGIN < alpha
+                {
+                    continue;
+                }
+                // Captures that lose material once recaptured are left out entirely
+                if game_move.promote.is_none() && !game.see_ge(&game_move, 0) {
+                    continue;
+                }
+            }
+            game.make_move_in_place(&game_move);
+            let score = -self.quiescence(game, ply + 1, -beta, -alpha);
+            game.unmake_move();
+            if self.aborted {
+                return Score::DRAW;
+            }
+            if score > max {
+                max = score;
+                if score > alpha {
+                    alpha = score;
+                    if alpha >= beta {
+                        break;
+                    }
                 }
             }
-            None => return 0,
         }
+        max
+    }
+}
+
+fn full_window() -> (Score, Score) {
+    (-Score::INFINITE, Score::INFINITE)
+}
+
+// What a fail-soft score found with the window `alpha`..`beta` says about the true score
+fn bound_of(score: Score, alpha: Score, beta: Score) -> Bound {
+    if score >= beta {
+        Bound::Lower
+    } else if score > alpha {
+        Bound::Exact
+    } else {
+        Bound::Upper
+    }
+}
+
+fn late_move_reduction(depth: usize, index: usize) -> usize {
+    LMR_TABLE[depth.min(LMR_TABLE_SIZE - 1)][index.min(LMR_TABLE_SIZE - 1)]
+}
+
+fn is_draw<B: Board>(game: &B) -> bool {
+    // 50-move rule or 3-fold repetition
+    game.get_half_moves() >= 100 || game.is_threefold_repetition()
+}
+
+// Static evaluation from the point of view of the side to move, `ply` is only needed to
+// score checkmates by their distance from the root
+fn evaluate<B: Board>(game: &B, valid_moves: &[GameMove1d], ply: usize) -> Score {
+    // Check if game is terminal
+    if valid_moves.is_empty() {
+        return match game.get_check() {
+            Some(_) => Score::mated_in(ply),
+            None => Score::DRAW,
+        };
     }
-    if is_draw(&game) {
-        return 0;
+    if is_draw(game) {
+        return Score::DRAW;
     }
     // Game is not terminal, get heuristic of the game
-    let endgame = is_endgame(&game);
+    let endgame = is_endgame(game);
     let mut curr_player_value: i32 = 0;
-    game.board.iter().enumerate().for_each(|(index, piece)| {
+    game.pieces().for_each(|(pos, piece)| {
         if piece.color == game.get_curr_player() {
-            curr_player_value += get_piece_value(piece, Position { value: index }, endgame)
+            curr_player_value += get_piece_value(&piece, pos, endgame)
         } else {
-            curr_player_value -= get_piece_value(piece, Position { value: index }, endgame)
+            curr_player_value -= get_piece_value(&piece, pos, endgame)
         }
     });
 
@@ -248,7 +1070,7 @@ fn evaluate(game: Mailbox, valid_moves: Vec<GameMove1d>) -> i32 {
         curr_player_value -= i32::from(castles.black_queen) * 40;
     }
 
-    curr_player_value
+    Score::cp(curr_player_value)
 }
 
 fn get_piece_value(piece: &Pieces, pos: Position, endgame: bool) -> i32 {
@@ -262,35 +1084,385 @@ fn get_piece_value(piece: &Pieces, pos: Position, endgame: bool) -> i32 {
     value
 }
 
-fn is_endgame(game: &Mailbox) -> bool {
-    let mut white_queen = false;
-    let mut black_queen = false;
-    let mut white_minors = 0;
-    let mut black_minors = 0;
-    game.board.iter().for_each(|piece| match piece {
-        Pieces {
-            piece_type: PieceTypes::Queen,
-            color: PieceColors::Black,
-        } => black_queen = true,
-        Pieces {
-            piece_type: PieceTypes::Queen,
-            color: PieceColors::White,
-        } => white_queen = true,
-        Pieces {
-            piece_type: PieceTypes::Knight,
-            color: x,
-        }
-        | Pieces {
-            piece_type: PieceTypes::Bishop,
-            color: x,
-        } => {
-            if x == &PieceColors::White {
-                white_minors += 1
-            } else {
-                black_minors += 1
+// Pieces other than pawns and the king of one side
+#[derive(Clone, Copy, Debug, Default)]
+struct PieceCount {
+    queens: usize,
+    rooks: usize,
+    minors: usize,
+}
+
+// Counts the pieces of both sides, white first
+fn count_pieces<B: Board>(game: &B) -> [PieceCount; 2] {
+    let mut counts = [PieceCount::default(); 2];
+    for (_, piece) in game.pieces() {
+        let side = match piece.color {
+            PieceColors::White => 0,
+            PieceColors::Black => 1,
+            PieceColors::Empty => continue,
+        };
+        match piece.piece_type {
+            PieceTypes::Queen => counts[side].queens += 1,
+            PieceTypes::Rook => counts[side].rooks += 1,
+            PieceTypes::Knight | PieceTypes::Bishop => counts[side].minors += 1,
+            _ => {}
+        }
+    }
+    counts
+}
+
+fn is_endgame<B: Board>(game: &B) -> bool {
+    count_pieces(game)
+        .iter()
+        .all(|side| side.queens == 0 || side.minors <= 1)
+}
+
+fn has_non_pawn_material<B: Board>(game: &B) -> bool {
+    let side = match game.get_curr_player() {
+        PieceColors::Black => 1,
+        _ => 0,
+    };
+    let count = count_pieces(game)[side];
+    count.queens + count.rooks + count.minors > 0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::ai::search_control::SearchLimits;
+    use crate::board::mailbox::Mailbox;
+
+    fn full_width_nega_max(game: &Mailbox, depth: usize, ply: usize) -> Score {
+        if depth == 0 {
+            let tt = TranspositionTable::new(1);
+            return Searcher::new(&tt, None).quiescence(
+                &mut game.clone(),
+                ply,
+                -Score::INFINITE,
+                Score::INFINITE,
+            );
+        }
+        let valid_moves = game.get_valid_moves();
+        if valid_moves.is_empty() {
+            return evaluate(game, &valid_moves, ply);
+        }
+        valid_moves
+            .iter()
+            .map(|mv| -full_width_nega_max(&game.make_move(mv), depth - 1, ply + 1))
+            .max()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_alpha_beta_matches_full_width() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+            "8/2k5/8/3q4/8/8/3R4/3K4 w - - 0 1",
+        ] {
+            let game = Mailbox::setup_board(Some(fen)).unwrap();
+            let tt = TranspositionTable::new(1);
+            let mut searcher = Searcher::new(&tt, None).with_pruning(Pruning::NONE);
+            searcher.extend = false;
+            for depth in 1..=2 {
+                let (_, score) = searcher.root_nega_max(&game, depth, None);
+                let expected = game
+                    .get_valid_moves()
+                    .iter()
+                    .map(|mv| -full_width_nega_max(&game.make_move(mv), depth - 1, 1))
+                    .max()
+                    .unwrap();
+                assert_eq!(score, expected, "fen: {fen}, depth: {depth}");
             }
         }
-        _ => {}
-    });
-    (!white_queen || white_minors <= 1) && (!black_queen || black_minors <= 1)
+    }
+
+    #[test]
+    fn test_quiescence_sees_recapture() {
+        // Qxd5 wins a pawn at depth 1 but loses the queen to exd5
+        let game = Mailbox::setup_board(Some("4k3/8/4p3/3p4/8/3Q4/8/4K3 w - - 0 1")).unwrap();
+        let tt = TranspositionTable::new(1);
+        let (best_move, score) = Searcher::new(&tt, None).root_nega_max(&game, 1, None);
+        assert_ne!(best_move, GameMove1d::from_str("d3d5").unwrap());
+        assert!(score > Score::DRAW);
+    }
+
+    #[test]
+    fn test_prefers_shortest_mate() {
+        // Rd8 mates at once, deeper searches also see slower mates after other moves
+        let game = Mailbox::setup_board(Some("6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1")).unwrap();
+        let tt = TranspositionTable::new(1);
+        let mut searcher = Searcher::new(&tt, None);
+        for depth in 1..=3 {
+            let (best_move, score) = searcher.root_nega_max(&game, depth, None);
+            assert_eq!(best_move, GameMove1d::from_str("d1d8").unwrap());
+            assert_eq!(score, Score::mate_in(1));
+            assert_eq!(score.to_string(), "mate 1");
+        }
+    }
+
+    #[test]
+    fn test_check_extensions_find_mate_sooner() {
+        // Rb7+ forces the king to the back rank, and Ra8 mates
+        let game = Mailbox::setup_board(Some("8/7k/R7/8/8/8/8/1R5K w - - 0 1")).unwrap();
+        let tt = TranspositionTable::new(1);
+        let mut searcher = Searcher::new(&tt, None);
+        let (_, score) = searcher.root_nega_max(&game, 2, None);
+        assert_eq!(score, Score::mate_in(3));
+        assert_eq!(searcher.principal_variation().len(), 3);
+
+        // Without extensions the quiet mate is beyond the horizon at this depth
+        let tt = TranspositionTable::new(1);
+        let mut searcher = Searcher::new(&tt, None);
+        searcher.extend = false;
+        assert!(!searcher.root_nega_max(&game, 2, None).1.is_mate());
+    }
+
+    #[test]
+    fn test_principal_variation_reaches_mate() {
+        let game = Mailbox::setup_board(Some("kbK5/pp6/1P6/8/8/8/8/R7 w - - 0 1")).unwrap();
+        let tt = TranspositionTable::new(1);
+        let mut searcher = Searcher::new(&tt, None);
+        let (best_move, score) = searcher.root_nega_max(&game, 3, None);
+        assert_eq!(score, Score::mate_in(3));
+
+        let pv = searcher.principal_variation().to_vec();
+        assert_eq!(pv.len(), 3);
+        assert_eq!(pv[0], best_move);
+        let end = pv.iter().fold(game, |position, mv| position.make_move(mv));
+        assert!(end.get_valid_moves().is_empty());
+        assert!(end.get_check().is_some());
+        assert!(searcher.seldepth >= 3);
+    }
+
+    #[test]
+    fn test_multi_pv_lines_are_ranked() {
+        let game = Mailbox::setup_board(Some(
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+        ))
+        .unwrap();
+        let engine = MailboxNegamax::default();
+        let lines = engine.analyse(&game, 2, 4);
+        assert_eq!(lines.len(), 4);
+        assert!(lines.windows(2).all(|pair| pair[0].score >= pair[1].score));
+        for (index, line) in lines.iter().enumerate() {
+            assert_eq!(line.pv[0], line.best_move);
+            assert!(lines[..index].iter().all(|x| x.best_move != line.best_move));
+        }
+
+        // The first line matches a single line search
+        let single = MailboxNegamax::default().analyse(&game, 2, 1);
+        assert_eq!(single[0].score, lines[0].score);
+
+        // Asking for more lines than legal moves returns every move
+        let game = Mailbox::setup_board(Some("k7/8/8/8/8/8/8/K7 w - - 0 1")).unwrap();
+        assert_eq!(engine.analyse(&game, 1, 10).len(), 3);
+    }
+
+    #[test]
+    fn test_most_cutoffs_come_from_the_first_move() {
+        let game = Mailbox::setup_board(Some(
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+        ))
+        .unwrap();
+        let (_, stats) = MailboxNegamax::default().analyse_with_stats(&game, 4, 1);
+        // Searching in generation order gets barely over a third
+        assert!(stats.cutoffs > 0);
+        assert!(stats.first_move_rate() > 0.6);
+    }
+
+    #[test]
+    fn test_each_pruning_saves_nodes() {
+        let game = Mailbox::setup_board(Some(
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+        ))
+        .unwrap();
+        let nodes = |pruning: Pruning| {
+            let tt = TranspositionTable::new(1);
+            let mut searcher = Searcher::new(&tt, None).with_pruning(pruning);
+            for depth in 1..=4 {
+                searcher.root_nega_max(&game, depth, None);
+            }
+            searcher.nodes
+        };
+        let full_width = nodes(Pruning::NONE);
+        let all = nodes(Pruning::ALL);
+        for pruning in [
+            Pruning {
+                null_move: true,
+                ..Pruning::NONE
+            },
+            Pruning {
+                late_move_reductions: true,
+                ..Pruning::NONE
+            },
+            Pruning {
+                reverse_futility: true,
+                ..Pruning::NONE
+            },
+            Pruning {
+                futility: true,
+                ..Pruning::NONE
+            },
+        ] {
+            let single = nodes(pruning);
+            assert!(single < full_width, "{pruning:?}");
+            assert!(all < single, "{pruning:?}");
+        }
+    }
+
+    #[test]
+    fn test_no_null_move_with_only_pawns() {
+        let pawns = Mailbox::setup_board(Some("8/5k2/5p2/8/8/5P2/5K2/8 w - - 0 1")).unwrap();
+        assert!(!has_non_pawn_material(&pawns));
+        assert!(is_endgame(&pawns));
+        // Only the side to move matters
+        let knight = Mailbox::setup_board(Some("8/5k2/5p2/8/8/5P2/5K2/6n1 w - - 0 1")).unwrap();
+        assert!(!has_non_pawn_material(&knight));
+        assert!(has_non_pawn_material(&knight.make_null_move()));
+    }
+
+    #[test]
+    fn test_aspiration_windows_widen_until_exact() {
+        let game = Mailbox::setup_board(Some(
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+        ))
+        .unwrap();
+        let root_moves = game.get_valid_moves();
+        let aspiration = |previous: Option<Score>| {
+            let tt = TranspositionTable::new(1);
+            let mut searcher = Searcher::new(&tt, None);
+            let mut failed = Vec::new();
+            let lines =
+                searcher.aspiration_lines(&game, 4, &root_moves, 1, previous, |_, lines| {
+                    failed.push(lines[0].bound)
+                });
+            (lines[0].clone(), failed)
+        };
+
+        let (exact, failed) = aspiration(None);
+        assert_eq!(exact.bound, Bound::Exact);
+        assert!(failed.is_empty());
+
+        // A guess far too high fails low until the window reaches down to the score
+        let guess = exact.score + 300;
+        let (line, failed) = aspiration(Some(guess));
+        assert_eq!(line.bound, Bound::Exact);
+        assert!(line.score < guess - ASPIRATION_WINDOW);
+        assert!(!failed.is_empty());
+        assert!(failed.iter().all(|bound| *bound == Bound::Upper));
+
+        let guess = exact.score - 300;
+        let (line, failed) = aspiration(Some(guess));
+        assert_eq!(line.bound, Bound::Exact);
+        assert!(line.score > guess + ASPIRATION_WINDOW);
+        assert!(!failed.is_empty());
+        assert!(failed.iter().all(|bound| *bound == Bound::Lower));
+    }
+
+    #[test]
+    fn test_helper_threads_share_the_search() {
+        let game = Mailbox::setup_board(Some("4k3/8/4p3/3p4/8/3Q4/8/4K3 w - - 0 1")).unwrap();
+        let mut engine = MailboxNegamax::default();
+        engine.set_option("Threads", Some("3")).unwrap();
+        let control = SearchControl::new(SearchLimits {
+            max_plies: Some(4),
+            ..Default::default()
+        });
+        let best_move = engine.uci_find_move(game.clone(), None, &control);
+        assert!(game.get_valid_moves().contains(&best_move));
+        assert_ne!(best_move, GameMove1d::from_str("d3d5").unwrap());
+    }
+
+    #[test]
+    fn test_helpers_respect_search_limits() {
+        let mut engine = MailboxNegamax::default();
+        engine.set_option("Threads", Some("4")).unwrap();
+        let node_limited = SearchLimits {
+            max_nodes: Some(20000),
+            ..Default::default()
+        };
+        assert_eq!(engine.search_threads(&node_limited), 1);
+        assert_eq!(engine.search_threads(&SearchLimits::default()), 4);
+
+        // Without a stop from the main thread a helper still ends at the depth limit
+        let game = Mailbox::setup_board(None).unwrap();
+        let control = SearchControl::new(SearchLimits::default());
+        let helper_nodes = AtomicUsize::new(0);
+        engine.helper_search(&game, 1, game.get_valid_moves(), 3, &control, &helper_nodes);
+        assert!(helper_nodes.load(Ordering::Relaxed) > 0);
+    }
+
+    #[test]
+    fn test_mate_search_without_moves() {
+        let engine = MailboxNegamax::default();
+        let control = SearchControl::new(SearchLimits::default());
+        let mated = Mailbox::setup_board(Some(
+            "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3",
+        ))
+        .unwrap();
+        let best_move = engine.uci_search_mate(mated, None, 2, &control);
+        assert_eq!(best_move, GameMove1d::default());
+
+        // Search moves that are not legal leave nothing to search either
+        let start = Mailbox::setup_board(None).unwrap();
+        let best_move = engine.uci_search_mate(start, Some(Vec::new()), 2, &control);
+        assert_eq!(best_move, GameMove1d::default());
+    }
+
+    #[test]
+    fn test_ponder_search_waits_for_ponderhit() {
+        let game = Mailbox::setup_board(None).unwrap();
+        let engine = MailboxNegamax::default();
+        let control = Arc::new(SearchControl::new(SearchLimits {
+            max_plies: Some(2),
+            ponder: true,
+            ..Default::default()
+        }));
+        let search = {
+            let (game, control) = (game.clone(), control.clone());
+            thread::spawn(move || engine.uci_find_move(game, None, &control))
+        };
+        thread::sleep(Duration::from_millis(200));
+        assert!(!search.is_finished());
+
+        control.ponderhit();
+        let best_move = search.join().unwrap();
+        assert!(game.get_valid_moves().contains(&best_move));
+    }
+
+    #[test]
+    fn test_stop_aborts_inside_iteration() {
+        let game = Mailbox::setup_board(None).unwrap();
+        let engine = MailboxNegamax::default();
+        let control = Arc::new(SearchControl::new(SearchLimits {
+            infinite: true,
+            ..Default::default()
+        }));
+        let search = {
+            let (game, control) = (game.clone(), control.clone());
+            thread::spawn(move || engine.uci_find_move(game, None, &control))
+        };
+        thread::sleep(Duration::from_millis(300));
+        assert!(!search.is_finished());
+
+        let stop_time = Instant::now();
+        control.stop();
+        let best_move = search.join().unwrap();
+        assert!(stop_time.elapsed() < Duration::from_millis(250));
+        assert!(game.get_valid_moves().contains(&best_move));
+    }
+
+    #[test]
+    fn test_node_limit_is_exact() {
+        let game = Mailbox::setup_board(None).unwrap();
+        let tt = TranspositionTable::new(1);
+        let mut searcher = Searcher::new(&tt, Some(1000));
+        let (best_move, _) = searcher.root_nega_max(&game, 6, None);
+        assert!(searcher.aborted);
+        assert_eq!(searcher.nodes, 1000);
+        assert!(game.get_valid_moves().contains(&best_move));
+    }
 }
@@ -1,12 +1,12 @@
-use crate::board::array2d::Array2D;
-use crate::utils::gamemove2d::GameMove2d;
+use crate::board::Board;
+use crate::utils::gamemove1d::GameMove1d;
 use rand::prelude::SliceRandom;
 use rand::thread_rng;
 
-pub(crate) struct Random2D {}
+pub struct Random2D {}
 
 impl Random2D {
-    pub fn find_move(&self, game: Array2D, _: bool, _: u128) -> GameMove2d {
+    pub fn find_move<T: Board>(&self, game: &T) -> GameMove1d {
         let valid_moves = game.get_valid_moves();
         *valid_moves.choose(&mut thread_rng()).unwrap()
     }
@@ -0,0 +1,138 @@
+use std::fmt;
+use std::ops::{Add, Neg, Sub};
+
+// Deepest ply a mate can be reported at, anything closer to MATE than this is a mate score
+pub const MAX_PLY: i32 = 256;
+
+const MATE: i32 = 32_000;
+const MATE_BOUND: i32 = MATE - MAX_PLY;
+
+/// Search score from the point of view of the side to move. Mates are stored as
+/// `MATE - ply`, counted from the root, so that shorter mates always score higher
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Score(i32);
+
+impl Score {
+    pub const DRAW: Score = Score(0);
+    // Wider than any mate so that it can be used as an open search window
+    pub const INFINITE: Score = Score(MATE + 1);
+
+    pub const fn cp(centipawns: i32) -> Self {
+        Score(centipawns)
+    }
+
+    /// Score for the side to move when it delivers mate `ply` plies from the root
+    pub const fn mate_in(ply: usize) -> Self {
+        Score(MATE - ply as i32)
+    }
+
+    /// Score for the side to move when it is checkmated `ply` plies from the root
+    pub const fn mated_in(ply: usize) -> Self {
+        Score(-MATE + ply as i32)
+    }
+
+    pub const fn value(self) -> i32 {
+        self.0
+    }
+
+    pub const fn is_mate(self) -> bool {
+        self.0.abs() >= MATE_BOUND && self.0.abs() <= MATE
+    }
+
+    /// Moves until mate as reported over UCI, negative when the side to move gets mated
+    pub const fn mate_moves(self) -> Option<i32> {
+        if !self.is_mate() {
+            None
+        } else if self.0 > 0 {
+            Some((MATE - self.0 + 1) / 2)
+        } else {
+            Some(-(MATE + self.0) / 2)
+        }
+    }
+
+    // Mate scores are converted from distance-to-root to distance-to-node before storing
+    // in the hash table, and back again when probing, so they stay correct when the same
+    // position is reached at another ply
+    pub fn to_tt(self, ply: usize) -> i32 {
+        match self.0 {
+            x if x >= MATE_BOUND => x + ply as i32,
+            x if x <= -MATE_BOUND => x - ply as i32,
+            x => x,
+        }
+    }
+
+    pub fn from_tt(stored: i32, ply: usize) -> Self {
+        match stored {
+            x if x >= MATE_BOUND => Score(x - ply as i32),
+            x if x <= -MATE_BOUND => Score(x + ply as i32),
+            x => Score(x),
+        }
+    }
+}
+
+impl Neg for Score {
+    type Output = Score;
+
+    fn neg(self) -> Score {
+        Score(-self.0)
+    }
+}
+
+impl Add<i32> for Score {
+    type Output = Score;
+
+    fn add(self, centipawns: i32) -> Score {
+        Score(self.0 + centipawns)
+    }
+}
+
+impl Sub<i32> for Score {
+    type Output = Score;
+
+    fn sub(self, centipawns: i32) -> Score {
+        Score(self.0 - centipawns)
+    }
+}
+
+/// Formats the score the way UCI expects it after `score`
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.mate_moves() {
+            Some(moves) => write!(f, "mate {}", moves),
+            None => write!(f, "cp {}", self.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shorter_mates_score_higher() {
+        assert!(Score::mate_in(1) > Score::mate_in(3));
+        assert!(Score::mated_in(2) < Score::mated_in(4));
+        assert!(Score::mated_in(4) < Score::cp(-5000));
+        assert!(Score::INFINITE > Score::mate_in(0));
+        assert_eq!(-Score::mate_in(5), Score::mated_in(5));
+    }
+
+    #[test]
+    fn test_uci_format() {
+        assert_eq!(Score::cp(-35).to_string(), "cp -35");
+        assert_eq!(Score::mate_in(1).to_string(), "mate 1");
+        assert_eq!(Score::mate_in(3).to_string(), "mate 2");
+        assert_eq!(Score::mated_in(2).to_string(), "mate -1");
+        assert_eq!(Score::mated_in(0).to_string(), "mate 0");
+    }
+
+    #[test]
+    fn test_tt_adjustment() {
+        // Mate found 3 plies below a node at ply 4, probed again at ply 6
+        let score = Score::mate_in(7);
+        assert_eq!(Score::from_tt(score.to_tt(4), 4), score);
+        assert_eq!(Score::from_tt(score.to_tt(4), 6), Score::mate_in(9));
+        assert_eq!(Score::from_tt((-score).to_tt(4), 6), Score::mated_in(9));
+        assert_eq!(Score::from_tt(Score::cp(150).to_tt(4), 9), Score::cp(150));
+    }
+}
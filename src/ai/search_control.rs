@@ -0,0 +1,149 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::time::Instant;
+
+use crate::ai::time_manager::TimeManager;
+
+/// Limits of a single `go` command, a default search runs until it is stopped
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SearchLimits {
+    pub time: TimeManager,
+    pub max_plies: Option<usize>,
+    pub max_nodes: Option<usize>,
+    // Search the position on the opponent's time until `ponderhit` or `stop` arrives
+    pub ponder: bool,
+    // Never answer before `stop`, even once there is nothing left to search
+    pub infinite: bool,
+}
+
+impl Default for SearchLimits {
+    fn default() -> Self {
+        SearchLimits {
+            time: TimeManager::infinite(),
+            max_plies: None,
+            max_nodes: None,
+            ponder: false,
+            infinite: false,
+        }
+    }
+}
+
+/// Shared between the UCI loop and every search thread. The UCI loop raises `stop` and
+/// `ponderhit`, the search polls `should_abort` while walking the tree.
+pub struct SearchControl {
+    pub limits: SearchLimits,
+    stop: AtomicBool,
+    pondering: AtomicBool,
+    // Start of our own clock, moved to the ponderhit when pondering
+    clock_start: Mutex<Instant>,
+    released: Condvar,
+}
+
+impl SearchControl {
+    pub fn new(limits: SearchLimits) -> Self {
+        SearchControl {
+            limits,
+            stop: AtomicBool::new(false),
+            pondering: AtomicBool::new(limits.ponder),
+            clock_start: Mutex::new(Instant::now()),
+            released: Condvar::new(),
+        }
+    }
+
+    pub fn stop(&self) {
+        let _clock = self.clock_start.lock().unwrap();
+        self.stop.store(true, Ordering::Relaxed);
+        self.released.notify_all();
+    }
+
+    /// The opponent played the predicted move, the search continues on our own clock
+    pub fn ponderhit(&self) {
+        let mut clock = self.clock_start.lock().unwrap();
+        *clock = Instant::now();
+        self.pondering.store(false, Ordering::Relaxed);
+        self.released.notify_all();
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+
+    pub fn is_pondering(&self) -> bool {
+        self.pondering.load(Ordering::Relaxed)
+    }
+
+    /// Milliseconds spent on our own clock
+    pub fn elapsed(&self) -> u128 {
+        self.clock_start.lock().unwrap().elapsed().as_millis()
+    }
+
+    /// True once the search has been stopped or has run out of time
+    pub fn should_abort(&self) -> bool {
+        self.is_stopped()
+            || (!self.is_pondering() && self.elapsed() >= self.limits.time.hard_limit())
+    }
+
+    /// Blocks a finished search until the GUI allows it to answer, which is on `stop`
+    /// for infinite searches and on `stop` or `ponderhit` while pondering
+    pub fn wait_until_released(&self) {
+        let clock = self.clock_start.lock().unwrap();
+        let _clock = self
+            .released
+            .wait_while(clock, |_| {
+                !self.is_stopped() && (self.is_pondering() || self.limits.infinite)
+            })
+            .unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::ai::time_manager::TimeControl;
+
+    #[test]
+    fn test_hard_limit_only_runs_on_our_clock() {
+        let time = TimeManager::new(
+            &TimeControl {
+                move_time: Some(20),
+                ..Default::default()
+            },
+            0,
+        );
+        let control = SearchControl::new(SearchLimits {
+            time,
+            ponder: true,
+            ..Default::default()
+        });
+        thread::sleep(Duration::from_millis(30));
+        assert!(!control.should_abort());
+
+        control.ponderhit();
+        assert!(!control.should_abort());
+        thread::sleep(Duration::from_millis(30));
+        assert!(control.should_abort());
+    }
+
+    #[test]
+    fn test_stop_releases_waiting_search() {
+        let control = Arc::new(SearchControl::new(SearchLimits {
+            infinite: true,
+            ..Default::default()
+        }));
+        let waiting = {
+            let control = control.clone();
+            thread::spawn(move || control.wait_until_released())
+        };
+        thread::sleep(Duration::from_millis(20));
+        assert!(!waiting.is_finished());
+        assert!(!control.should_abort());
+
+        control.stop();
+        waiting.join().unwrap();
+        assert!(control.should_abort());
+    }
+}
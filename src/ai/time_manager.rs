@@ -0,0 +1,213 @@
+use crate::ai::score::Score;
+use crate::utils::gamemove1d::GameMove1d;
+use crate::utils::pieces::PieceColors;
+
+// Number of moves a sudden death game is assumed to still last
+const DEFAULT_MOVES_TO_GO: u128 = 30;
+// Share of the remaining time, in percent, that a single move may ever use
+const MAX_USAGE_PERCENT: u128 = 80;
+// How far past the soft limit an unstable search may run
+const HARD_LIMIT_FACTOR: u128 = 4;
+// Score drop in centipawns at which the time extension for a failing line is maxed out
+const MAX_SCORE_DROP: f64 = 100.0;
+
+/// Clock information of a `go` command for the side to move, all times in milliseconds
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TimeControl {
+    pub time: Option<u128>,
+    pub increment: u128,
+    pub moves_to_go: Option<u128>,
+    pub move_time: Option<u128>,
+}
+
+impl TimeControl {
+    /// Reads wtime/btime/winc/binc/movestogo/movetime from the arguments of `go`
+    pub fn parse(args: &[&str], side: PieceColors) -> Self {
+        let value = |name: &str| {
+            args.iter()
+                .position(|&arg| arg == name)
+                .and_then(|index| args.get(index + 1))
+                .and_then(|value| value.parse::<i64>().ok())
+                // Some GUIs send negative times once the clock has run out
+                .map(|value| value.max(0) as u128)
+        };
+        let (time, increment) = match side {
+            PieceColors::Black => ("btime", "binc"),
+            _ => ("wtime", "winc"),
+        };
+        TimeControl {
+            time: value(time),
+            increment: value(increment).unwrap_or(0),
+            moves_to_go: value("movestogo"),
+            move_time: value("movetime"),
+        }
+    }
+}
+
+/// Decides how long a search may run. The soft limit is checked between iterations and
+/// grows while the best move keeps changing or the score is dropping, the hard limit is
+/// never exceeded.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimeManager {
+    soft_limit: u128,
+    hard_limit: u128,
+    best_move_changes: f64,
+    score_drop: f64,
+    last_best: Option<(GameMove1d, Score)>,
+}
+
+impl TimeManager {
+    pub fn new(control: &TimeControl, move_overhead: u128) -> Self {
+        let (soft_limit, hard_limit) = match (control.move_time, control.time) {
+            (Some(move_time), _) => {
+                let limit = move_time.saturating_sub(move_overhead).max(1);
+                (limit, limit)
+            }
+            (None, Some(time)) => {
+                let available = time.saturating_sub(move_overhead);
+                let max_usage = (available * MAX_USAGE_PERCENT / 100).max(1);
+                let moves_to_go = control.moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+                let soft_limit =
+                    (available / moves_to_go + control.increment * 3 / 4).clamp(1, max_usage);
+                (soft_limit, (soft_limit * HARD_LIMIT_FACTOR).min(max_usage))
+            }
+            (None, None) => (u128::MAX, u128::MAX),
+        };
+        TimeManager {
+            soft_limit,
+            hard_limit,
+            best_move_changes: 0.0,
+            score_drop: 0.0,
+            last_best: None,
+        }
+    }
+
+    /// A search without any time limit
+    pub fn infinite() -> Self {
+        TimeManager::new(&TimeControl::default(), 0)
+    }
+
+    pub fn is_infinite(&self) -> bool {
+        self.hard_limit == u128::MAX
+    }
+
+    pub fn hard_limit(&self) -> u128 {
+        self.hard_limit
+    }
+
+    /// Soft limit scaled by the current instability of the search
+    pub fn soft_limit(&self) -> u128 {
+        if self.is_infinite() {
+            return u128::MAX;
+        }
+        let scale =
+            (1.0 + self.best_move_changes) * (1.0 + self.score_drop.min(MAX_SCORE_DROP) / 100.0);
+        ((self.soft_limit as f64 * scale) as u128).min(self.hard_limit)
+    }
+
+    /// Records the result of a finished iteration
+    pub fn on_iteration(&mut self, best_move: GameMove1d, score: Score) {
+        // Older changes matter less the deeper the search gets
+        self.best_move_changes /= 2.0;
+        if let Some((last_move, last_score)) = self.last_best {
+            if last_move != best_move {
+                self.best_move_changes += 1.0;
+            }
+            self.score_drop = if score.is_mate() || last_score.is_mate() {
+                0.0
+            } else {
+                (last_score.value() - score.value()).max(0) as f64
+            };
+        }
+        self.last_best = Some((best_move, score));
+    }
+
+    /// Whether another iteration, expected to take `predicted_iteration` milliseconds, is
+    /// worth starting after `elapsed` milliseconds
+    pub fn should_start_iteration(&self, elapsed: u128, predicted_iteration: u128) -> bool {
+        elapsed < self.soft_limit() && elapsed.saturating_add(predicted_iteration) < self.hard_limit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn manager(args: &str, overhead: u128) -> TimeManager {
+        let args: Vec<&str> = args.split(' ').collect();
+        TimeManager::new(&TimeControl::parse(&args, PieceColors::White), overhead)
+    }
+
+    #[test]
+    fn test_parse() {
+        let args: Vec<&str> = "go wtime 1000 btime 2000 winc 10 binc 20 movestogo 5"
+            .split(' ')
+            .collect();
+        let black = TimeControl::parse(&args, PieceColors::Black);
+        assert_eq!(black.time, Some(2000));
+        assert_eq!(black.increment, 20);
+        assert_eq!(black.moves_to_go, Some(5));
+        assert_eq!(black.move_time, None);
+        assert_eq!(
+            TimeControl::parse(&["go", "wtime", "-30"], PieceColors::White).time,
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn test_limits() {
+        // Sudden death spreads the clock over the default number of moves
+        let sudden_death = manager("go wtime 60000 btime 60000 winc 1000", 0);
+        assert_eq!(sudden_death.soft_limit(), 60000 / 30 + 750);
+        assert_eq!(sudden_death.hard_limit(), (60000 / 30 + 750) * 4);
+
+        // With one move to go almost everything may be used, but never the whole clock
+        let last_move = manager("go wtime 10000 movestogo 1", 100);
+        assert_eq!(last_move.soft_limit(), 9900 * 80 / 100);
+        assert_eq!(last_move.hard_limit(), 9900 * 80 / 100);
+
+        // Repeating controls with more moves left than the default spread the clock thinner
+        let repeating = manager("go wtime 60000 movestogo 60", 0);
+        assert_eq!(repeating.soft_limit(), 60000 / 60);
+        assert_eq!(repeating.hard_limit(), 60000 / 60 * 4);
+
+        let fixed = manager("go movetime 500", 30);
+        assert_eq!((fixed.soft_limit(), fixed.hard_limit()), (470, 470));
+
+        // The overhead can eat the whole budget, but a search always gets some time
+        let flagging = manager("go wtime 20", 50);
+        assert_eq!(flagging.hard_limit(), 1);
+
+        assert!(manager("go depth 5", 10).is_infinite());
+        assert!(TimeManager::infinite().should_start_iteration(1 << 40, 1 << 40));
+    }
+
+    #[test]
+    fn test_instability_extends_soft_limit() {
+        let mut time = manager("go wtime 30000", 0);
+        let base = time.soft_limit();
+        let e4 = GameMove1d::from_str("e2e4").unwrap();
+        let d4 = GameMove1d::from_str("d2d4").unwrap();
+
+        time.on_iteration(e4, Score::cp(20));
+        time.on_iteration(e4, Score::cp(25));
+        assert_eq!(time.soft_limit(), base);
+
+        time.on_iteration(d4, Score::cp(25));
+        assert_eq!(time.soft_limit(), base * 2);
+
+        // A dropping score extends further, but never past the hard limit
+        time.on_iteration(e4, Score::cp(-75));
+        assert!(time.soft_limit() > base * 2);
+        assert!(time.soft_limit() <= time.hard_limit());
+
+        time.on_iteration(e4, Score::cp(-75));
+        time.on_iteration(e4, Score::cp(-75));
+        assert!(time.soft_limit() < base * 2);
+        assert!(time.should_start_iteration(0, 0));
+        assert!(!time.should_start_iteration(time.soft_limit(), 0));
+        assert!(!time.should_start_iteration(0, time.hard_limit()));
+    }
+}
@@ -0,0 +1,314 @@
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+
+use crate::ai::score::Score;
+use crate::utils::gamemove1d::GameMove1d;
+use crate::utils::pieces::PieceTypes;
+
+pub const DEFAULT_HASH_MB: usize = 16;
+
+const ENTRIES_PER_BUCKET: usize = 2;
+const ENTRY_BYTES: usize = 2 * std::mem::size_of::<AtomicU64>();
+const GENERATION_MASK: u8 = 0x1F;
+// Number of buckets inspected when estimating how full the table is
+const HASHFULL_SAMPLE: usize = 500;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TTEntry {
+    pub depth: u8,
+    pub bound: Bound,
+    // Mate scores are stored relative to this node rather than the root
+    score: i32,
+    best_move: u32,
+    generation: u8,
+}
+
+impl TTEntry {
+    /// Looks up the stored best move among the legal moves of the position, which also
+    /// guards against using a move from a colliding key
+    pub fn best_move(&self, moves: &[GameMove1d]) -> Option<GameMove1d> {
+        if self.best_move == 0 {
+            return None;
+        }
+        moves
+            .iter()
+            .find(|mv| encode_move(mv) == self.best_move)
+            .copied()
+    }
+
+    /// Entry score from the point of view of the node at `ply`
+    pub fn score(&self, ply: usize) -> Score {
+        Score::from_tt(self.score, ply)
+    }
+
+    // Data layout: score (32) | move (17) | depth (8) | bound (2) | generation (5)
+    fn pack(&self) -> u64 {
+        let bound = match self.bound {
+            Bound::Exact => 1u64,
+            Bound::Lower => 2,
+            Bound::Upper => 3,
+        };
+        (self.score as u32 as u64)
+            | (self.best_move as u64) << 32
+            | (self.depth as u64) << 49
+            | bound << 57
+            | ((self.generation & GENERATION_MASK) as u64) << 59
+    }
+
+    fn unpack(data: u64) -> Option<Self> {
+        let bound = match (data >> 57) & 0x3 {
+            1 => Bound::Exact,
+            2 => Bound::Lower,
+            3 => Bound::Upper,
+            _ => return None,
+        };
+        Some(TTEntry {
+            depth: (data >> 49) as u8,
+            bound,
+            score: data as u32 as i32,
+            best_move: ((data >> 32) & 0x1FFFF) as u32,
+            generation: (data >> 59) as u8 & GENERATION_MASK,
+        })
+    }
+}
+
+struct Slot {
+    // Key is stored xor'd with the data so that torn writes from concurrent searches
+    // are detected as a key mismatch instead of returning corrupt data
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+impl Slot {
+    fn empty() -> Self {
+        Slot {
+            key: AtomicU64::new(0),
+            data: AtomicU64::new(0),
+        }
+    }
+
+    fn load(&self) -> Option<(u64, TTEntry)> {
+        let data = self.data.load(Ordering::Relaxed);
+        let key = self.key.load(Ordering::Relaxed) ^ data;
+        TTEntry::unpack(data).map(|entry| (key, entry))
+    }
+
+    fn save(&self, key: u64, entry: &TTEntry) {
+        let data = entry.pack();
+        self.key.store(key ^ data, Ordering::Relaxed);
+        self.data.store(data, Ordering::Relaxed);
+    }
+
+    fn clear(&self) {
+        self.key.store(0, Ordering::Relaxed);
+        self.data.store(0, Ordering::Relaxed);
+    }
+}
+
+// Buckets hold a depth-preferred slot, which keeps the most expensive result seen for
+// the current search, and an always-replace slot for everything else
+pub struct TranspositionTable {
+    buckets: Vec<[Slot; ENTRIES_PER_BUCKET]>,
+    generation: AtomicU8,
+}
+
+impl TranspositionTable {
+    pub fn new(size_mb: usize) -> Self {
+        let bucket_count = (size_mb * 1024 * 1024 / (ENTRY_BYTES * ENTRIES_PER_BUCKET)).max(1);
+        let mut buckets = Vec::with_capacity(bucket_count);
+        buckets.resize_with(bucket_count, || [Slot::empty(), Slot::empty()]);
+        TranspositionTable {
+            buckets,
+            generation: AtomicU8::new(0),
+        }
+    }
+
+    pub fn clear(&self) {
+        self.buckets.iter().flatten().for_each(Slot::clear);
+        self.generation.store(0, Ordering::Relaxed);
+    }
+
+    /// Marks the start of a new search so entries from older searches get replaced first
+    pub fn new_search(&self) {
+        let generation = self.generation.load(Ordering::Relaxed);
+        self.generation.store(
+            generation.wrapping_add(1) & GENERATION_MASK,
+            Ordering::Relaxed,
+        );
+    }
+
+    pub fn probe(&self, key: u64) -> Option<TTEntry> {
+        self.bucket(key)
+            .iter()
+            .filter_map(Slot::load)
+            .find(|(slot_key, _)| *slot_key == key)
+            .map(|(_, entry)| entry)
+    }
+
+    pub fn store(
+        &self,
+        key: u64,
+        depth: usize,
+        ply: usize,
+        bound: Bound,
+        score: Score,
+        best_move: Option<&GameMove1d>,
+    ) {
+        let generation = self.generation.load(Ordering::Relaxed);
+        let [depth_preferred, always_replace] = self.bucket(key);
+        let previous = depth_preferred.load();
+
+        // Keep the old best move if this search did not find one for the same position
+        let mut best_move = best_move.map_or(0, encode_move);
+        if best_move == 0 {
+            if let Some((_, entry)) = [previous, always_replace.load()]
+                .into_iter()
+                .flatten()
+                .find(|(slot_key, _)| *slot_key == key)
+            {
+                best_move = entry.best_move;
+            }
+        }
+
+        let entry = TTEntry {
+            depth: depth.min(u8::MAX as usize) as u8,
+            bound,
+            score: score.to_tt(ply),
+            best_move,
+            generation,
+        };
+        let replace_deep = match previous {
+            None => true,
+            Some((slot_key, old)) => {
+                slot_key == key || old.generation != generation || entry.depth >= old.depth
+            }
+        };
+        if replace_deep {
+            depth_preferred.save(key, &entry);
+        } else {
+            always_replace.save(key, &entry);
+        }
+    }
+
+    /// Permille of the table used by the current search, estimated from the first buckets
+    pub fn hashfull(&self) -> usize {
+        let generation = self.generation.load(Ordering::Relaxed);
+        let sampled = self.buckets.len().min(HASHFULL_SAMPLE);
+        let used = self.buckets[..sampled]
+            .iter()
+            .flatten()
+            .filter_map(Slot::load)
+            .filter(|(_, entry)| entry.generation == generation)
+            .count();
+        used * 1000 / (sampled * ENTRIES_PER_BUCKET)
+    }
+
+    fn bucket(&self, key: u64) -> &[Slot; ENTRIES_PER_BUCKET] {
+        // Maps the key onto the table without requiring a power of two size
+        let index = ((key as u128 * self.buckets.len() as u128) >> 64) as usize;
+        &self.buckets[index]
+    }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        TranspositionTable::new(DEFAULT_HASH_MB)
+    }
+}
+
+// Packs the squares and promotion of a move into 17 bits, 0 is reserved for no move
+fn encode_move(mv: &GameMove1d) -> u32 {
+    let promotion = match mv.promote.map(|piece| piece.piece_type) {
+        Some(PieceTypes::Knight) => 1,
+        Some(PieceTypes::Bishop) => 2,
+        Some(PieceTypes::Rook) => 3,
+        Some(PieceTypes::Queen) => 4,
+        _ => 0,
+    };
+    (mv.start.value as u32) | (mv.end.value as u32) << 7 | promotion << 14
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::mailbox::Mailbox;
+    use crate::board::Board;
+
+    #[test]
+    fn test_store_and_probe() {
+        let tt = TranspositionTable::new(1);
+        let game = Mailbox::setup_board(None).unwrap();
+        let moves = game.get_valid_moves();
+        tt.store(
+            game.get_key(),
+            5,
+            0,
+            Bound::Lower,
+            Score::cp(-42),
+            Some(&moves[3]),
+        );
+
+        let entry = tt.probe(game.get_key()).unwrap();
+        assert_eq!(entry.depth, 5);
+        assert_eq!(entry.bound, Bound::Lower);
+        assert_eq!(entry.score(0), Score::cp(-42));
+        assert_eq!(entry.best_move(&moves), Some(moves[3]));
+        assert!(tt.probe(game.get_key() ^ 1).is_none());
+
+        tt.clear();
+        assert!(tt.probe(game.get_key()).is_none());
+    }
+
+    #[test]
+    fn test_depth_preferred_replacement() {
+        let tt = TranspositionTable::new(1);
+        // Keys sharing the top bits land in the same bucket
+        let deep_key = 0xABCD_0000_0000_0001;
+        let shallow_key = 0xABCD_0000_0000_0002;
+        tt.store(deep_key, 10, 0, Bound::Exact, Score::cp(1), None);
+        tt.store(shallow_key, 2, 0, Bound::Exact, Score::cp(2), None);
+        assert_eq!(tt.probe(deep_key).unwrap().depth, 10);
+        assert_eq!(tt.probe(shallow_key).unwrap().depth, 2);
+
+        // A new search may overwrite the deep entry
+        tt.new_search();
+        tt.store(shallow_key, 1, 0, Bound::Exact, Score::cp(3), None);
+        assert!(tt.probe(deep_key).is_none());
+    }
+
+    #[test]
+    fn test_mate_scores_are_ply_adjusted() {
+        let tt = TranspositionTable::new(1);
+        let key = 0x1234_5678_9ABC_DEF0;
+        tt.store(key, 3, 4, Bound::Exact, Score::mate_in(7), None);
+        let entry = tt.probe(key).unwrap();
+        assert_eq!(entry.score(4), Score::mate_in(7));
+        // The same position two plies deeper is two plies further from mate
+        assert_eq!(entry.score(6), Score::mate_in(9));
+    }
+
+    #[test]
+    fn test_hashfull_counts_current_search() {
+        let tt = TranspositionTable::new(1);
+        assert_eq!(tt.hashfull(), 0);
+        for key in 0..4000u64 {
+            tt.store(
+                key.wrapping_mul(0x9E37_79B9_7F4A_7C15),
+                1,
+                0,
+                Bound::Exact,
+                Score::DRAW,
+                None,
+            );
+        }
+        assert!(tt.hashfull() > 0);
+        tt.new_search();
+        assert_eq!(tt.hashfull(), 0);
+    }
+}
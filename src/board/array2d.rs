@@ -1,14 +1,20 @@
-use crate::utils::gamemove2d::to_let;
-use crate::utils::gamemove2d::to_num;
+use crate::board::fen::{Fen, START_POSITION};
+use crate::board::Board;
+use crate::utils::castling::CastleRights;
+use crate::utils::checks::Checks;
+use crate::utils::chess_errors::ChessError;
+use crate::utils::gamemove1d::{self, GameMove1d};
 use crate::utils::gamemove2d::CastleTypes;
 use crate::utils::gamemove2d::GameMove2d;
 use crate::utils::gamemove2d::PassantTypes;
 use crate::utils::pieces::{
     PieceColors, PieceColors::*, PieceTypes, PieceTypes::*, Pieces, BLACK, WHITE,
 };
-use std::cmp::PartialEq;
+use crate::utils::position::Position;
+use crate::utils::zobrist::ZOBRIST;
+use std::fmt;
+use std::mem;
 
-const START_POSITION: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
 const KNIGHT_OFFSETS: [[i32; 2]; 8] = [
     [-2, -1],
     [-2, 1],
@@ -37,7 +43,7 @@ const EMPTY_PIECE: Pieces = Pieces {
 };
 
 /* Game state representation and member functions */
-#[derive(Clone, Debug, Copy)]
+#[derive(Clone, Debug)]
 pub struct Array2D {
     pub board: [[Pieces; 8]; 8],
     //False represents white, true represents black
@@ -46,13 +52,30 @@ pub struct Array2D {
     castling_rights: [bool; 4],
     //None if no en passant is possible, Some if possible by taking the position given with a pawn
     en_passant: Option<[usize; 2]>,
-    half_moves: usize,
-    full_moves: usize,
+    half_moves: u16,
+    full_moves: u16,
     check: Option<bool>,
     white_king: [usize; 2],
     black_king: [usize; 2],
     white_attack_map: [[u8; 8]; 8],
     black_attack_map: [[u8; 8]; 8],
+    //The position before every move made in place, most recent last
+    history: Vec<Array2D>,
+}
+
+impl fmt::Display for Array2D {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut board_string = String::new();
+        board_string.push_str("-----------------\n");
+        for row in self.board.iter().rev() {
+            board_string.push('|');
+            for piece in row {
+                board_string.extend(format!("{piece}|").chars());
+            }
+            board_string.push_str("\n-----------------\n");
+        }
+        write!(f, "{}", board_string)
+    }
 }
 
 impl Array2D {
@@ -192,58 +215,26 @@ impl Array2D {
     }
 }
 
-impl Array2D {
-    fn setup_board(fen: Option<&str>) -> Self {
-        let mut fields = fen.unwrap_or(START_POSITION).split_ascii_whitespace();
+impl Board for Array2D {
+    fn setup_board(fen: Option<&str>) -> Result<Self, ChessError> {
+        let fen: Fen = fen.unwrap_or(START_POSITION).parse()?;
         //Read board positions
-        let temp = fields.next().unwrap();
-        let board = temp.split('/');
         let mut board_state: [[Pieces; 8]; 8] = [[EMPTY_PIECE; 8]; 8];
-        for (index, line) in board.enumerate() {
-            let mut file: usize = 0;
-            for piece in line.chars() {
-                //Move the index forward when there are spaces
-                if piece.is_ascii_digit() {
-                    file += piece.to_digit(10).unwrap() as usize;
-                }
-                //Add piece to board
-                else {
-                    board_state[7 - index][file] = Pieces::from(&piece);
-                    file += 1;
-                }
+        for (i, row) in board_state.iter_mut().enumerate() {
+            for (j, square) in row.iter_mut().enumerate() {
+                *square = fen.piece_at(to_position([i, j]));
             }
         }
-
-        //Read current move
-        let player: bool = match fields.next().unwrap() {
-            "b" => BLACK,
-            "w" => WHITE,
-            _ => panic!("Invalid FEN String"),
-        };
-
-        //Read castling rights
-        let temp = fields.next().unwrap();
-        let rights: [bool; 4] = [
-            temp.contains('K'),
-            temp.contains('Q'),
-            temp.contains('k'),
-            temp.contains('q'),
+        let player = fen.curr_player == Black;
+        let rights = fen.castling_rights;
+        let rights = [
+            rights.white_king,
+            rights.white_queen,
+            rights.black_king,
+            rights.black_queen,
         ];
-
-        //Read En Passant targets
-        let temp = fields.next().unwrap();
-        let passant: Option<[usize; 2]> = if !temp.eq("-") {
-            let mut chars = temp.chars();
-            let col = to_num(chars.next().unwrap());
-            let row = (chars.next().unwrap().to_digit(10).unwrap() - 1) as usize;
-            Some([row, col])
-        } else {
-            None
-        };
-
-        //Read move numbers
-        let half_moves = fields.next().unwrap().parse::<usize>().unwrap();
-        let full_moves = fields.next().unwrap().parse::<usize>().unwrap();
+        let passant = fen.en_passant.map(to_square);
+        let (half_moves, full_moves) = (fen.half_moves, fen.full_moves);
 
         //Find king positions
         let mut black_king = [0, 0];
@@ -278,15 +269,144 @@ impl Array2D {
             black_king,
             white_attack_map,
             black_attack_map,
+            history: Vec::new(),
         };
 
         //Calculate if a king is in check
         game.check = who_is_checked(&game);
 
-        game
+        Ok(game)
+    }
+
+    fn get_valid_moves(&self) -> Vec<GameMove1d> {
+        self.valid_moves_2d().iter().map(to_move_1d).collect()
+    }
+
+    fn make_move_in_place(&mut self, mov: &GameMove1d) {
+        let mut next = self.make_move_2d(&self.to_move_2d(mov));
+        next.history = mem::take(&mut self.history);
+        let previous = mem::replace(self, next);
+        self.history.push(previous);
+    }
+
+    fn make_null_move_in_place(&mut self) {
+        let mut next = Array2D {
+            history: mem::take(&mut self.history),
+            ..*self
+        };
+        next.curr_move = !next.curr_move;
+        next.en_passant = None;
+        next.half_moves = 0;
+        if !next.curr_move {
+            next.full_moves = next.full_moves.saturating_add(1)
+        }
+        let previous = mem::replace(self, next);
+        self.history.push(previous);
+    }
+
+    fn unmake_move(&mut self) {
+        let previous = self.history.pop().expect("No move to unmake");
+        let history = mem::take(&mut self.history);
+        *self = previous;
+        self.history = history;
+    }
+
+    fn get_curr_player(&self) -> PieceColors {
+        match self.curr_move {
+            BLACK => Black,
+            WHITE => White,
+        }
+    }
+
+    fn get_check(&self) -> Option<Checks> {
+        self.check.map(|player| match player {
+            BLACK => Checks::Black,
+            WHITE => Checks::White,
+        })
+    }
+
+    fn get_castle_rights(&self) -> CastleRights {
+        let [white_king, white_queen, black_king, black_queen] = self.castling_rights;
+        CastleRights {
+            white_king,
+            white_queen,
+            black_king,
+            black_queen,
+        }
+    }
+
+    fn get_en_passant(&self) -> Option<Position> {
+        self.en_passant.map(to_position)
+    }
+
+    fn get_half_moves(&self) -> u16 {
+        self.half_moves
+    }
+
+    fn get_full_moves(&self) -> u16 {
+        self.full_moves
+    }
+
+    //The key is built from scratch, this board is kept for its simplicity rather than speed
+    fn get_key(&self) -> u64 {
+        let mut key = 0;
+        for (i, row) in self.board.iter().enumerate() {
+            for (j, piece) in row.iter().enumerate() {
+                key ^= ZOBRIST.piece(*piece, to_position([i, j]));
+            }
+        }
+        if self.curr_move == BLACK {
+            key ^= ZOBRIST.black_to_move;
+        }
+        key ^= ZOBRIST.castle_rights(self.get_castle_rights());
+        key ^= ZOBRIST.en_passant(self.get_en_passant());
+        key
+    }
+
+    fn previous_keys(&self) -> impl Iterator<Item = u64> + '_ {
+        self.history.iter().rev().map(|previous| previous.get_key())
+    }
+
+    fn piece_at(&self, pos: Position) -> Pieces {
+        let [row, col] = to_square(pos);
+        self.board[row][col]
+    }
+}
+
+impl Array2D {
+    //Moves from UCI carry no castling or en passant flags, so both are recognised from the board
+    fn to_move_2d(&self, mov: &GameMove1d) -> GameMove2d {
+        let start = to_square(mov.start);
+        let end = to_square(mov.end);
+        let piece = self.board[start[0]][start[1]];
+        let castle = match (piece.piece_type, start, end) {
+            (King, [0, 4], [0, 6]) => Some(CastleTypes::WhiteKing),
+            (King, [0, 4], [0, 2]) => Some(CastleTypes::WhiteQueen),
+            (King, [7, 4], [7, 6]) => Some(CastleTypes::BlackKing),
+            (King, [7, 4], [7, 2]) => Some(CastleTypes::BlackQueen),
+            _ => None,
+        };
+        let passant = match piece.piece_type {
+            Pawn if self.en_passant == Some(end) => {
+                Some(PassantTypes::PassantCapture([start[0], end[1]]))
+            }
+            Pawn if start[0].abs_diff(end[0]) == 2 => Some(PassantTypes::PassantAvailable([
+                (start[0] + end[0]) / 2,
+                start[1],
+            ])),
+            _ => None,
+        };
+        GameMove2d {
+            start,
+            end,
+            castle,
+            promote: mov.promote,
+            passant,
+            capture: mov.capture,
+        }
     }
 
-    fn get_valid_moves(&self) -> Vec<GameMove2d> {
+    fn valid_moves_2d(&self) -> Vec<GameMove2d> {
         let mut moves = Vec::new();
 
         for i in 0..8 {
@@ -349,9 +469,12 @@ impl Array2D {
         moves
     }
 
-    fn make_move(&self, mov: &GameMove2d) -> Array2D {
-        //Expects mov to be a valid move
-        let mut result = *self;
+    fn make_move_2d(&self, mov: &GameMove2d) -> Array2D {
+        //Expects mov to be a valid move, the result starts without a history
+        let mut result = Array2D {
+            history: Vec::new(),
+            ..*self
+        };
         let piece = self.board[mov.start[0]][mov.start[1]];
         let capture = result.board[mov.end[0]][mov.end[1]].piece_type != PieceTypes::Empty;
 
@@ -388,7 +511,9 @@ impl Array2D {
             }
         }
 
-        //Check if it was a passant move and remove the pawn accordingly
+        //Check if it was a passant move and remove the pawn accordingly, the target only
+        //lasts for one move
+        result.en_passant = None;
         if let Some(passant_type) = mov.passant {
             match passant_type {
                 PassantTypes::PassantAvailable(pos) => result.en_passant = Some(pos),
@@ -414,28 +539,25 @@ impl Array2D {
             _ => {}
         }
 
-        //Update who is in check
-        result.check = who_is_checked(&result);
-
         //Update castling rights
-        if result.board[0][4].piece_type != King && result.board[0][4].color != White {
+        if result.board[0][4].piece_type != King || result.board[0][4].color != White {
             result.castling_rights[0] = false;
             result.castling_rights[1] = false;
         }
-        if result.board[7][4].piece_type != King && result.board[7][4].color != Black {
+        if result.board[7][4].piece_type != King || result.board[7][4].color != Black {
             result.castling_rights[2] = false;
             result.castling_rights[3] = false;
         }
-        if result.board[0][0].piece_type != Rook && result.board[0][0].color != White {
+        if result.board[0][0].piece_type != Rook || result.board[0][0].color != White {
             result.castling_rights[1] = false;
         }
-        if result.board[0][7].piece_type != Rook && result.board[0][7].color != White {
+        if result.board[0][7].piece_type != Rook || result.board[0][7].color != White {
             result.castling_rights[0] = false;
         }
-        if result.board[7][0].piece_type != Rook && result.board[7][0].color != Black {
+        if result.board[7][0].piece_type != Rook || result.board[7][0].color != Black {
             result.castling_rights[3] = false;
         }
-        if result.board[7][7].piece_type != Rook && result.board[7][7].color != Black {
+        if result.board[7][7].piece_type != Rook || result.board[7][7].color != Black {
             result.castling_rights[2] = false;
         }
 
@@ -443,34 +565,21 @@ impl Array2D {
         if capture || piece.piece_type == Pawn {
             result.half_moves = 0;
         } else {
-            result.half_moves += 1;
+            result.half_moves = result.half_moves.saturating_add(1);
         }
         //Update full moves
         if !result.curr_move {
-            result.full_moves += 1
+            result.full_moves = result.full_moves.saturating_add(1)
         }
 
         // Calculate Attack Maps
         [result.white_attack_map, result.black_attack_map] =
             Array2D::generate_attack_maps(result.board);
 
-        result
-    }
-
-    fn get_check(&self) -> Option<bool> {
-        self.check
-    }
-
-    fn get_curr_player(&self) -> bool {
-        self.curr_move
-    }
-
-    fn get_board_as_2d(&self) -> [[Pieces; 8]; 8] {
-        self.board
-    }
+        //Update who is in check
+        result.check = who_is_checked(&result);
 
-    fn get_castle_rights(&self) -> [bool; 4] {
-        self.castling_rights
+        result
     }
 }
 
@@ -501,25 +610,21 @@ fn find_moves(
             };
             if game.board[row as usize][col as usize].piece_type != PieceTypes::Empty {
                 match game.board[row as usize][col as usize] {
-                    Pieces { color: Black, .. } => {
-                        if !player {
-                            moves.push(GameMove2d {
-                                start,
-                                end: [row as usize, col as usize],
-                                capture: true,
-                                ..Default::default()
-                            });
-                        }
+                    Pieces { color: Black, .. } if !player => {
+                        moves.push(GameMove2d {
+                            start,
+                            end: [row as usize, col as usize],
+                            capture: true,
+                            ..Default::default()
+                        });
                     }
-                    Pieces { color: White, .. } => {
-                        if player {
-                            moves.push(GameMove2d {
-                                start,
-                                end: [row as usize, col as usize],
-                                capture: true,
-                                ..Default::default()
-                            });
-                        }
+                    Pieces { color: White, .. } if player => {
+                        moves.push(GameMove2d {
+                            start,
+                            end: [row as usize, col as usize],
+                            capture: true,
+                            ..Default::default()
+                        });
                     }
                     _ => {}
                 }
@@ -536,12 +641,12 @@ fn find_moves(
     if player {
         moves
             .into_iter()
-            .filter(|x| !is_black_checked(&game.make_move(x)))
+            .filter(|x| !is_black_checked(&game.make_move_2d(x)))
             .collect()
     } else {
         moves
             .into_iter()
-            .filter(|x| !is_white_checked(&game.make_move(x)))
+            .filter(|x| !is_white_checked(&game.make_move_2d(x)))
             .collect()
     }
 }
@@ -582,8 +687,8 @@ fn king_moves(game: &Array2D, start: [usize; 2], player: bool) -> Vec<GameMove2d
                     end: [0, 6],
                     ..Default::default()
                 };
-                if !is_white_checked(&game.make_move(&mov1))
-                    && !is_white_checked(&game.make_move(&mov2))
+                if !is_white_checked(&game.make_move_2d(&mov1))
+                    && !is_white_checked(&game.make_move_2d(&mov2))
                 {
                     moves.push(GameMove2d {
                         start,
@@ -609,8 +714,8 @@ fn king_moves(game: &Array2D, start: [usize; 2], player: bool) -> Vec<GameMove2d
                     end: [0, 2],
                     ..Default::default()
                 };
-                if !is_white_checked(&game.make_move(&mov1))
-                    && !is_white_checked(&game.make_move(&mov2))
+                if !is_white_checked(&game.make_move_2d(&mov1))
+                    && !is_white_checked(&game.make_move_2d(&mov2))
                 {
                     moves.push(GameMove2d {
                         start,
@@ -638,8 +743,8 @@ fn king_moves(game: &Array2D, start: [usize; 2], player: bool) -> Vec<GameMove2d
                     end: [7, 6],
                     ..Default::default()
                 };
-                if !is_black_checked(&game.make_move(&mov1))
-                    && !is_black_checked(&game.make_move(&mov2))
+                if !is_black_checked(&game.make_move_2d(&mov1))
+                    && !is_black_checked(&game.make_move_2d(&mov2))
                 {
                     moves.push(GameMove2d {
                         start,
@@ -665,8 +770,8 @@ fn king_moves(game: &Array2D, start: [usize; 2], player: bool) -> Vec<GameMove2d
                     end: [7, 2],
                     ..Default::default()
                 };
-                if !is_black_checked(&game.make_move(&mov1))
-                    && !is_black_checked(&game.make_move(&mov2))
+                if !is_black_checked(&game.make_move_2d(&mov1))
+                    && !is_black_checked(&game.make_move_2d(&mov2))
                 {
                     moves.push(GameMove2d {
                         start,
@@ -688,7 +793,6 @@ fn pawn_moves(game: &Array2D, start: [usize; 2], player: bool) -> Vec<GameMove2d
     }
     // Settings set up
     let forward = if player { -1 } else { 1 };
-    let no_capture = if player { [7, 7] } else { [0, 0] };
     let check_function = if player {
         is_black_checked
     } else {
@@ -707,47 +811,8 @@ fn pawn_moves(game: &Array2D, start: [usize; 2], player: bool) -> Vec<GameMove2d
             end: [row as usize, col as usize],
             ..Default::default()
         };
-        if !check_function(&game.make_move(&mov)) {
-            if row == [7, 0][player as usize] {
-                moves.push(GameMove2d {
-                    start,
-                    end: [row as usize, col as usize],
-                    promote: Some(Pieces {
-                        piece_type: Queen,
-                        color,
-                    }),
-                    ..Default::default()
-                });
-                moves.push(GameMove2d {
-                    start,
-                    end: [row as usize, col as usize],
-                    promote: Some(Pieces {
-                        piece_type: Rook,
-                        color,
-                    }),
-                    ..Default::default()
-                });
-                moves.push(GameMove2d {
-                    start,
-                    end: [row as usize, col as usize],
-                    promote: Some(Pieces {
-                        piece_type: Bishop,
-                        color,
-                    }),
-                    ..Default::default()
-                });
-                moves.push(GameMove2d {
-                    start,
-                    end: [row as usize, col as usize],
-                    promote: Some(Pieces {
-                        piece_type: Knight,
-                        color,
-                    }),
-                    ..Default::default()
-                });
-            } else {
-                moves.push(mov);
-            }
+        if !check_function(&game.make_move_2d(&mov)) {
+            push_pawn_move(&mut moves, mov, color);
         }
     }
     //Pawn moves forward two - available only if the pawn is on it's starting square, then the two squares in front must be empty. Cannot promote with this move
@@ -767,170 +832,61 @@ fn pawn_moves(game: &Array2D, start: [usize; 2], player: bool) -> Vec<GameMove2d
                 passant,
                 ..Default::default()
             };
-            if !check_function(&game.make_move(&mov)) {
+            if !check_function(&game.make_move_2d(&mov)) {
                 moves.push(mov);
             }
         }
     }
-    //Pawn captures to the left - available only if there is an opposing piece on that square
-    let row = start[0] as i32 + forward;
-    let col = start[1] as i32 - 1;
-    //If a passant capture is available, get the space coordinates - otherwise the square that can't be captured by current player's pawns
-    let passant_pos = if let Some(passant_pos) = game.en_passant {
-        passant_pos
-    } else {
-        no_capture
-    };
-    let passant_cap = [row as usize, col as usize] == passant_pos;
-    if is_in_bounds([row, col])
-        && (game.board[row as usize][col as usize].piece_type != PieceTypes::Empty || passant_cap)
-    {
-        let mov = GameMove2d {
-            start,
-            end: [row as usize, col as usize],
-            ..Default::default()
-        };
-        if passant_cap && !check_function(&game.make_move(&mov)) {
-            //Passant captures cannot promote
-            moves.push(GameMove2d {
+    //Pawn captures to either side - available if there is an opposing piece on that square,
+    //or it is the en passant target
+    for side in [-1, 1] {
+        let row = start[0] as i32 + forward;
+        let col = start[1] as i32 + side;
+        if !is_in_bounds([row, col]) {
+            continue;
+        }
+        let end = [row as usize, col as usize];
+        let target = game.board[end[0]][end[1]];
+        let mov = if game.en_passant == Some(end) {
+            //The captured pawn stands beside the capturing one
+            GameMove2d {
                 start,
-                end: passant_pos,
-                passant: Some(PassantTypes::PassantCapture([
-                    (row + -forward) as usize,
-                    col as usize,
-                ])),
+                end,
+                passant: Some(PassantTypes::PassantCapture([start[0], end[1]])),
+                capture: true,
                 ..Default::default()
-            });
-        } else {
-            match game.board[row as usize][col as usize] {
-                Pieces { color: player, .. } => {
-                    if player != color && !check_function(&game.make_move(&mov)) {
-                        if row == [7, 0][player as usize] {
-                            moves.push(GameMove2d {
-                                start,
-                                end: [row as usize, col as usize],
-                                promote: Some(Pieces {
-                                    piece_type: Queen,
-                                    color,
-                                }),
-                                ..Default::default()
-                            });
-                            moves.push(GameMove2d {
-                                start,
-                                end: [row as usize, col as usize],
-                                promote: Some(Pieces {
-                                    piece_type: Rook,
-                                    color,
-                                }),
-                                ..Default::default()
-                            });
-                            moves.push(GameMove2d {
-                                start,
-                                end: [row as usize, col as usize],
-                                promote: Some(Pieces {
-                                    piece_type: Bishop,
-                                    color,
-                                }),
-                                ..Default::default()
-                            });
-                            moves.push(GameMove2d {
-                                start,
-                                end: [row as usize, col as usize],
-                                promote: Some(Pieces {
-                                    piece_type: Knight,
-                                    color,
-                                }),
-                                ..Default::default()
-                            });
-                        } else {
-                            moves.push(mov);
-                        }
-                    }
-                }
-            };
-        }
-    }
-    //Pawn captures to the right - available only if there is a piece on that square
-    let row = start[0] as i32 + forward;
-    let col = start[1] as i32 + 1;
-    //If a passant capture is available, get the space coordinates - otherwise the square that can't be captured by current player's pawns
-    let passant_pos = if let Some(passant_pos) = game.en_passant {
-        passant_pos
-    } else {
-        no_capture
-    };
-    let passant_cap = [row as usize, col as usize] == passant_pos;
-    if is_in_bounds([row, col])
-        && (game.board[row as usize][col as usize].piece_type != PieceTypes::Empty || passant_cap)
-    {
-        let mov = GameMove2d {
-            start,
-            end: [row as usize, col as usize],
-            ..Default::default()
-        };
-        if passant_cap && !check_function(&game.make_move(&mov)) {
-            //Passant captures cannot promote
-            moves.push(GameMove2d {
+            }
+        } else if target.piece_type != PieceTypes::Empty && target.color != color {
+            GameMove2d {
                 start,
-                end: passant_pos,
-                passant: Some(PassantTypes::PassantCapture([
-                    (row + -forward) as usize,
-                    col as usize,
-                ])),
+                end,
+                capture: true,
                 ..Default::default()
-            });
+            }
         } else {
-            match game.board[row as usize][col as usize] {
-                Pieces { color: player, .. } => {
-                    if player != color && !check_function(&game.make_move(&mov)) {
-                        if row == [7, 0][player as usize] {
-                            moves.push(GameMove2d {
-                                start,
-                                end: [row as usize, col as usize],
-                                promote: Some(Pieces {
-                                    piece_type: Queen,
-                                    color,
-                                }),
-                                ..Default::default()
-                            });
-                            moves.push(GameMove2d {
-                                start,
-                                end: [row as usize, col as usize],
-                                promote: Some(Pieces {
-                                    piece_type: Rook,
-                                    color,
-                                }),
-                                ..Default::default()
-                            });
-                            moves.push(GameMove2d {
-                                start,
-                                end: [row as usize, col as usize],
-                                promote: Some(Pieces {
-                                    piece_type: Bishop,
-                                    color,
-                                }),
-                                ..Default::default()
-                            });
-                            moves.push(GameMove2d {
-                                start,
-                                end: [row as usize, col as usize],
-                                promote: Some(Pieces {
-                                    piece_type: Knight,
-                                    color,
-                                }),
-                                ..Default::default()
-                            });
-                        } else {
-                            moves.push(mov);
-                        }
-                    }
-                }
-            };
+            continue;
+        };
+        if !check_function(&game.make_move_2d(&mov)) {
+            push_pawn_move(&mut moves, mov, color);
         }
     }
     moves
 }
 
+//Adds `mov`, or one move for every piece a pawn can promote to once it reaches the last row
+fn push_pawn_move(moves: &mut Vec<GameMove2d>, mov: GameMove2d, color: PieceColors) {
+    if mov.end[0] != 0 && mov.end[0] != 7 {
+        moves.push(mov);
+        return;
+    }
+    for piece_type in [Queen, Rook, Bishop, Knight] {
+        moves.push(GameMove2d {
+            promote: Some(Pieces { piece_type, color }),
+            ..mov
+        });
+    }
+}
+
 fn is_in_bounds(pos: [i32; 2]) -> bool {
     if pos[0] > 7 || pos[0] < 0 || pos[1] > 7 || pos[1] < 0 {
         return false;
@@ -946,6 +902,40 @@ fn is_black_checked(game: &Array2D) -> bool {
     game.white_attack_map[game.black_king[0]][game.black_king[1]] > 0
 }
 
+//Converts a [row, col] square into a 10x12 mailbox position
+fn to_position([row, col]: [usize; 2]) -> Position {
+    Position {
+        value: 21 + row * 10 + col,
+    }
+}
+
+fn to_square(pos: Position) -> [usize; 2] {
+    [pos.value / 10 - 2, pos.value % 10 - 1]
+}
+
+fn to_move_1d(mov: &GameMove2d) -> GameMove1d {
+    GameMove1d {
+        start: to_position(mov.start),
+        end: to_position(mov.end),
+        castle: mov.castle.map(|castle| match castle {
+            CastleTypes::WhiteKing => gamemove1d::CastleTypes::WhiteKing,
+            CastleTypes::WhiteQueen => gamemove1d::CastleTypes::WhiteQueen,
+            CastleTypes::BlackKing => gamemove1d::CastleTypes::BlackKing,
+            CastleTypes::BlackQueen => gamemove1d::CastleTypes::BlackQueen,
+        }),
+        promote: mov.promote,
+        passant: mov.passant.map(|passant| match passant {
+            PassantTypes::PassantCapture(square) => {
+                gamemove1d::PassantTypes::PassantCapture(to_position(square))
+            }
+            PassantTypes::PassantAvailable(square) => {
+                gamemove1d::PassantTypes::PassantAvailable(to_position(square))
+            }
+        }),
+        capture: mov.capture,
+    }
+}
+
 fn who_is_checked(game: &Array2D) -> Option<bool> {
     //White King
     if is_white_checked(game) {
@@ -970,14 +960,14 @@ fn digit_manipulation() {
         ('g', 6),
         ('h', 7),
     ] {
-        assert_eq!(to_num(character), number);
-        assert_eq!(to_let(number), character);
+        assert_eq!(crate::utils::gamemove2d::to_num(character), number);
+        assert_eq!(crate::utils::gamemove2d::to_let(number), character);
     }
 }
 
 #[test]
 fn test_move_generation() {
-    let game = Array2D::setup_board(None);
+    let game = Array2D::setup_board(None).unwrap();
     assert_eq!(game.get_valid_moves().len(), 20)
 }
 
@@ -1003,7 +993,7 @@ fn test_attack_map_generation() {
         [1, 1, 1, 4, 4, 1, 1, 1],
         [0, 1, 1, 1, 1, 1, 1, 0],
     ];
-    let game = Array2D::setup_board(None);
+    let game = Array2D::setup_board(None).unwrap();
     assert_eq!(STARTING_ATTACKS_WHITE, game.white_attack_map);
     assert_eq!(STARTING_ATTACKS_BLACK, game.black_attack_map);
 }
@@ -0,0 +1,460 @@
+use lazy_static::lazy_static;
+
+use crate::utils::zobrist::split_mix;
+
+// Magic numbers of every square, found once with `find_magic` from `MAGIC_SEED` so the
+// tables can be built without searching at start up
+const ROOK_MAGICS: [u64; 64] = [
+    0xA080001820400080,
+    0x0040002000401000,
+    0x0180300160008008,
+    0x0480040800801001,
+    0x2A00081084204200,
+    0x0480018012003400,
+    0x0600010082000428,
+    0x420002250C018042,
+    0x0040800040002080,
+    0x000040002000500C,
+    0x2002004022001080,
+    0x0026002200400810,
+    0x2000808008000400,
+    0x0022000200883104,
+    0x2C88808001000200,
+    0x1112000080420104,
+    0x0100908000400020,
+    0x0080808020004000,
+    0x0008410010200300,
+    0x0014808010000801,
+    0x0080050011004800,
+    0x00D1010002080400,
+    0xA08004000A300158,
+    0x1000120005288244,
+    0x020C400080248002,
+    0x4020411200220082,
+    0x8028100080200881,
+    0x1210001100090020,
+    0x005A005200084520,
+    0x0080040080020080,
+    0x0002000200840148,
+    0x440B210A00006884,
+    0x0880401028800080,
+    0x2000802008804000,
+    0x2160001041002900,
+    0x201020400A001200,
+    0x8018010009001104,
+    0x2480800400800200,
+    0x0000010804000210,
+    0x0020008042003104,
+    0x0000802040008000,
+    0x0010002000404000,
+    0x0001001020010041,
+    0x8840100009010022,
+    0x8048004020040400,
+    0x2000040002008080,
+    0x0803000200010084,
+    0x0010004400820001,
+    0xA881410720800100,
+    0x0008208A00450600,
+    0x0000802000100080,
+    0x004408A240920200,
+    0x6000800400080080,
+    0x0020040002008080,
+    0x8003000A00245500,
+    0x0100842081004200,
+    0x0000201840820102,
+    0x0011002040008019,
+    0x001181C20020501A,
+    0x1C10014488201101,
+    0x0002002004110802,
+    0x0881000204000801,
+    0x2000880142100094,
+    0x000154050022C082,
+];
+const BISHOP_MAGICS: [u64; 64] = [
+    0x0002021418048103,
+    0x0023100102108001,
+    0x1622008112000818,
+    0x06108912010002D0,
+    0x4002021000202400,
+    0x41C1010840012100,
+    0x0028841002D10100,
+    0x2820818409114080,
+    0x0082242048312111,
+    0xA028680828004050,
+    0x0030100142142020,
+    0x8100044040880800,
+    0x9004040422200240,
+    0x2400011118400422,
+    0x0030204402201008,
+    0x4280468A4C022081,
+    0x0540041010810140,
+    0x4030000882808400,
+    0x4010000104082045,
+    0xC004048804101401,
+    0x0102023401210801,
+    0x0000400200422000,
+    0x0882100100906408,
+    0x1001000441009008,
+    0x40D1400028020442,
+    0x040808203C1002AC,
+    0x1000500818068010,
+    0x2084080020202040,
+    0x0001010104104000,
+    0x0008020000404200,
+    0x004829000A414810,
+    0x2584104082260204,
+    0x0828044480D0E080,
+    0x0101442006300100,
+    0x4000840112300040,
+    0x0220A00800010104,
+    0x8010490042040040,
+    0x0000A20080441001,
+    0x4290010120404C00,
+    0x802801004A090042,
+    0x0001042221044004,
+    0x440410A808004410,
+    0x0010840048010101,
+    0x1010002018020900,
+    0x05102004A0822C00,
+    0x0040040802882210,
+    0x1A101400E0808C01,
+    0x3101015400800100,
+    0x20020801D8080000,
+    0x0009804C42200000,
+    0x0001282422280004,
+    0x1040000084040021,
+    0x0090042003440002,
+    0x8000084810042001,
+    0x00411001120080D0,
+    0x0820480541002910,
+    0xB211008041201000,
+    0x020000288808484C,
+    0x1108801080580800,
+    0x0020100280840C40,
+    0x04400801210A4C02,
+    0x8004048520140110,
+    0x004C100408008408,
+    0x23502022042821A0,
+];
+// Seed of the search that runs if a stored magic number ever stops fitting its square
+const MAGIC_SEED: u64 = 0x2545_F491_4F6C_DD1D;
+
+const KNIGHT_STEPS: [(i8, i8); 8] = [
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+];
+const KING_STEPS: [(i8, i8); 8] = [
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+];
+const ROOK_DIRECTIONS: [(i8, i8); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+const BISHOP_DIRECTIONS: [(i8, i8); 4] = [(1, 1), (-1, 1), (-1, -1), (1, -1)];
+
+lazy_static! {
+    pub(crate) static ref ATTACKS: AttackTables = AttackTables::new();
+}
+
+// Hashes the blockers of a slider on one square into its slice of the shared attack table
+struct Magic {
+    mask: u64,
+    magic: u64,
+    shift: u32,
+    offset: usize,
+}
+
+impl Magic {
+    fn index(&self, occupied: u64) -> usize {
+        self.offset + ((occupied & self.mask).wrapping_mul(self.magic) >> self.shift) as usize
+    }
+}
+
+/// Attack sets of every piece on every square, squares numbered a1 = 0 to h8 = 63.
+/// Sliding attacks are looked up with magic multiplication using the stored `ROOK_MAGICS`
+/// and `BISHOP_MAGICS`, a new magic is only searched for if a stored one does not fit.
+pub(crate) struct AttackTables {
+    knight: [u64; 64],
+    king: [u64; 64],
+    // Squares attacked by a white and a black pawn
+    pawn: [[u64; 64]; 2],
+    rook: Vec<Magic>,
+    bishop: Vec<Magic>,
+    sliding: Vec<u64>,
+    // Squares strictly between two squares on a common line, empty otherwise
+    between: Vec<[u64; 64]>,
+    // The whole line through two squares, empty if they do not share one
+    line: Vec<[u64; 64]>,
+}
+
+impl AttackTables {
+    fn new() -> Self {
+        let mut knight = [0; 64];
+        let mut king = [0; 64];
+        let mut pawn = [[0; 64]; 2];
+        for square in 0..64 {
+            knight[square] = step_attacks(square, &KNIGHT_STEPS);
+            king[square] = step_attacks(square, &KING_STEPS);
+            pawn[0][square] = step_attacks(square, &[(-1, 1), (1, 1)]);
+            pawn[1][square] = step_attacks(square, &[(-1, -1), (1, -1)]);
+        }
+
+        let mut state = MAGIC_SEED;
+        let mut sliding = Vec::new();
+        let rook = (0..64)
+            .map(|square| {
+                let magic = ROOK_MAGICS[square];
+                find_magic(square, &ROOK_DIRECTIONS, magic, &mut state, &mut sliding)
+            })
+            .collect();
+        let bishop = (0..64)
+            .map(|square| {
+                let magic = BISHOP_MAGICS[square];
+                find_magic(square, &BISHOP_DIRECTIONS, magic, &mut state, &mut sliding)
+            })
+            .collect();
+
+        let mut between = vec![[0; 64]; 64];
+        let mut line = vec![[0; 64]; 64];
+        for from in 0..64 {
+            for directions in [ROOK_DIRECTIONS, BISHOP_DIRECTIONS] {
+                for direction in directions {
+                    let full_line = 1 << from
+                        | ray_attacks(from, &[direction], 0)
+                        | ray_attacks(from, &[(-direction.0, -direction.1)], 0);
+                    let mut passed = 0;
+                    let mut current = from;
+                    while let Some(to) = offset(current, direction) {
+                        between[from][to] = passed;
+                        line[from][to] = full_line;
+                        passed |= 1 << to;
+                        current = to;
+                    }
+                }
+            }
+        }
+
+        AttackTables {
+            knight,
+            king,
+            pawn,
+            rook,
+            bishop,
+            sliding,
+            between,
+            line,
+        }
+    }
+
+    pub(crate) fn knight(&self, square: usize) -> u64 {
+        self.knight[square]
+    }
+
+    pub(crate) fn king(&self, square: usize) -> u64 {
+        self.king[square]
+    }
+
+    /// Squares attacked by a pawn of `color`, 0 for white and 1 for black
+    pub(crate) fn pawn(&self, color: usize, square: usize) -> u64 {
+        self.pawn[color][square]
+    }
+
+    pub(crate) fn rook(&self, square: usize, occupied: u64) -> u64 {
+        self.sliding[self.rook[square].index(occupied)]
+    }
+
+    pub(crate) fn bishop(&self, square: usize, occupied: u64) -> u64 {
+        self.sliding[self.bishop[square].index(occupied)]
+    }
+
+    pub(crate) fn queen(&self, square: usize, occupied: u64) -> u64 {
+        self.rook(square, occupied) | self.bishop(square, occupied)
+    }
+
+    pub(crate) fn between(&self, from: usize, to: usize) -> u64 {
+        self.between[from][to]
+    }
+
+    pub(crate) fn line(&self, from: usize, to: usize) -> u64 {
+        self.line[from][to]
+    }
+}
+
+/// Iterates over the squares of a bitboard from a1 to h8
+pub(crate) fn squares(mut bitboard: u64) -> impl Iterator<Item = usize> {
+    std::iter::from_fn(move || {
+        if bitboard == 0 {
+            return None;
+        }
+        let square = bitboard.trailing_zeros() as usize;
+        bitboard &= bitboard - 1;
+        Some(square)
+    })
+}
+
+fn offset(square: usize, (file_step, rank_step): (i8, i8)) -> Option<usize> {
+    let file = (square % 8) as i8 + file_step;
+    let rank = (square / 8) as i8 + rank_step;
+    ((0..8).contains(&file) && (0..8).contains(&rank)).then_some((rank * 8 + file) as usize)
+}
+
+fn step_attacks(square: usize, steps: &[(i8, i8)]) -> u64 {
+    steps
+        .iter()
+        .filter_map(|step| offset(square, *step))
+        .fold(0, |attacks, to| attacks | 1 << to)
+}
+
+// Walks every direction one square at a time up to and including the first blocker
+fn ray_attacks(square: usize, directions: &[(i8, i8)], occupied: u64) -> u64 {
+    let mut attacks = 0;
+    for direction in directions {
+        let mut current = square;
+        while let Some(next) = offset(current, *direction) {
+            attacks |= 1 << next;
+            if occupied & 1 << next != 0 {
+                break;
+            }
+            current = next;
+        }
+    }
+    attacks
+}
+
+// Blockers on the last square of a ray never change the attacks, so they are left out of
+// the mask to keep the tables small
+fn relevant_mask(square: usize, directions: &[(i8, i8)]) -> u64 {
+    let mut mask = 0;
+    for direction in directions {
+        let mut current = square;
+        while let Some(next) = offset(current, *direction) {
+            if offset(next, *direction).is_none() {
+                break;
+            }
+            mask |= 1 << next;
+            current = next;
+        }
+    }
+    mask
+}
+
+// Starting with `candidate`, tries sparse random numbers until one maps every blocker subset
+// of the mask to a slot without mixing up two different attack sets, then appends the
+// slots to `table`
+fn find_magic(
+    square: usize,
+    directions: &[(i8, i8)],
+    candidate: u64,
+    state: &mut u64,
+    table: &mut Vec<u64>,
+) -> Magic {
+    let mask = relevant_mask(square, directions);
+    let bits = mask.count_ones();
+    let shift = 64 - bits;
+
+    // Every subset of the mask, enumerated with the carry rippler trick
+    let mut subsets = Vec::with_capacity(1 << bits);
+    let mut subset: u64 = 0;
+    loop {
+        subsets.push((subset, ray_attacks(square, directions, subset)));
+        subset = subset.wrapping_sub(mask) & mask;
+        if subset == 0 {
+            break;
+        }
+    }
+
+    // A slider always attacks at least one square, so 0 marks an unused slot
+    let mut slots = vec![0; 1 << bits];
+    let mut magic = candidate;
+    loop {
+        if (mask.wrapping_mul(magic) >> 56).count_ones() < 6 {
+            magic = sparse_random(state);
+            continue;
+        }
+
+        slots.fill(0);
+        let fits = subsets.iter().all(|(occupied, attacks)| {
+            let slot = &mut slots[(occupied.wrapping_mul(magic) >> shift) as usize];
+            if *slot == 0 {
+                *slot = *attacks;
+            }
+            *slot == *attacks
+        });
+        if fits {
+            let offset = table.len();
+            table.extend_from_slice(&slots);
+            return Magic {
+                mask,
+                magic,
+                shift,
+                offset,
+            };
+        }
+        magic = sparse_random(state);
+    }
+}
+
+// Magic numbers with few bits set are far more likely to work
+fn sparse_random(state: &mut u64) -> u64 {
+    let mut random = u64::MAX;
+    for _ in 0..3 {
+        let next;
+        (*state, next) = split_mix(*state);
+        random &= next;
+    }
+    random
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sliding_attacks_match_rays() {
+        let mut state = 7;
+        for _ in 0..200 {
+            let occupied;
+            (state, occupied) = split_mix(state);
+            // Thin out the blockers so long rays get tested as well
+            let occupied = occupied & split_mix(state).1;
+            for square in 0..64 {
+                assert_eq!(
+                    ATTACKS.rook(square, occupied),
+                    ray_attacks(square, &ROOK_DIRECTIONS, occupied)
+                );
+                assert_eq!(
+                    ATTACKS.bishop(square, occupied),
+                    ray_attacks(square, &BISHOP_DIRECTIONS, occupied)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_stored_magics_fit() {
+        for square in 0..64 {
+            assert_eq!(ATTACKS.rook[square].magic, ROOK_MAGICS[square]);
+            assert_eq!(ATTACKS.bishop[square].magic, BISHOP_MAGICS[square]);
+        }
+    }
+
+    #[test]
+    fn test_between_and_line() {
+        // a1 to h8, c3 to f3 and two squares that do not share a line
+        assert_eq!(ATTACKS.between(0, 63).count_ones(), 6);
+        assert_eq!(ATTACKS.between(18, 21), 1 << 19 | 1 << 20);
+        assert_eq!(ATTACKS.between(0, 17), 0);
+        assert_eq!(ATTACKS.line(18, 21), 0xFF << 16);
+        assert_eq!(ATTACKS.line(0, 17), 0);
+    }
+}
@@ -0,0 +1,791 @@
+use std::fmt;
+
+use crate::board::mailbox::Mailbox;
+use crate::board::Board;
+use crate::utils::castling::CastleRights;
+use crate::utils::checks::Checks;
+use crate::utils::chess_errors::ChessError;
+use crate::utils::gamemove1d::{CastleTypes, GameMove1d, PassantTypes};
+use crate::utils::pieces::{PieceColors, PieceTypes, Pieces};
+use crate::utils::position::Position;
+use crate::utils::zobrist::{square_index, ZOBRIST};
+
+use attacks::{squares, ATTACKS};
+
+mod attacks;
+
+const EMPTY_PIECE: Pieces = Pieces {
+    piece_type: PieceTypes::Empty,
+    color: PieceColors::Empty,
+};
+
+// Indices into the piece sets of each color
+const PAWN: usize = 0;
+const KNIGHT: usize = 1;
+const BISHOP: usize = 2;
+const ROOK: usize = 3;
+const QUEEN: usize = 4;
+const KING: usize = 5;
+
+// Piece type of each index above
+const PIECE_TYPES: [PieceTypes; 6] = [
+    PieceTypes::Pawn,
+    PieceTypes::Knight,
+    PieceTypes::Bishop,
+    PieceTypes::Rook,
+    PieceTypes::Queen,
+    PieceTypes::King,
+];
+
+const PROMOTABLE_PIECES: [PieceTypes; 4] = [
+    PieceTypes::Queen,
+    PieceTypes::Rook,
+    PieceTypes::Bishop,
+    PieceTypes::Knight,
+];
+
+const RANK_1: u64 = 0xFF;
+const RANK_2: u64 = RANK_1 << 8;
+const RANK_7: u64 = RANK_1 << 48;
+const RANK_8: u64 = RANK_1 << 56;
+
+/// Board made of one 64 bit set per piece type and color, squares numbered a1 = 0 to
+/// h8 = 63. Moves are the same `GameMove1d` the `Mailbox` uses.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bitboard {
+    // Squares of every piece type for white and black, see `piece_index`
+    pieces: [[u64; 6]; 2],
+    colors: [u64; 2],
+    // The piece on each square, so captures do not have to search the sets
+    squares: [Pieces; 64],
+    curr_player: PieceColors,
+    castling_rights: CastleRights,
+    en_passant: Option<usize>,
+    pub half_moves: u16,
+    pub full_moves: u16,
+    check: Option<Checks>,
+    // One entry for every move made since the position was set up, most recent last
+    history: Vec<UndoInfo>,
+    key: u64,
+}
+
+// Everything `unmake_move` needs to restore the position before a move, a null move
+// leaves `moved` empty
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct UndoInfo {
+    from: usize,
+    to: usize,
+    moved: Pieces,
+    captured: Pieces,
+    captured_square: usize,
+    castle: Option<CastleTypes>,
+    castling_rights: CastleRights,
+    en_passant: Option<usize>,
+    half_moves: u16,
+    full_moves: u16,
+    check: Option<Checks>,
+    key: u64,
+}
+
+impl From<&Mailbox> for Bitboard {
+    fn from(mailbox: &Mailbox) -> Self {
+        let mut board = Bitboard {
+            pieces: [[0; 6]; 2],
+            colors: [0; 2],
+            squares: [EMPTY_PIECE; 64],
+            curr_player: mailbox.curr_player,
+            castling_rights: mailbox.castling_rights,
+            en_passant: mailbox.en_passant.map(square_index),
+            half_moves: mailbox.half_moves,
+            full_moves: mailbox.full_moves,
+            check: None,
+            history: Vec::new(),
+            key: 0,
+        };
+        for square in 0..64 {
+            let piece = mailbox.board[to_position(square).value];
+            if piece.piece_type != PieceTypes::Empty {
+                board.put(square, piece);
+            }
+        }
+        board.check = board.find_check();
+        board.key = board.compute_key();
+        board
+    }
+}
+
+impl fmt::Display for Bitboard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut board_string = String::new();
+        board_string.push_str("-----------------\n");
+        for row in self.squares.chunks(8).rev() {
+            board_string.push('|');
+            for piece in row {
+                board_string.extend(format!("{piece}|").chars());
+            }
+            board_string.push_str("\n-----------------\n");
+        }
+        write!(f, "{}", board_string)
+    }
+}
+
+impl Board for Bitboard {
+    fn setup_board(fen: Option<&str>) -> Result<Self, ChessError> {
+        Mailbox::setup_board(fen).map(|mailbox| Bitboard::from(&mailbox))
+    }
+
+    fn get_valid_moves(&self) -> Vec<GameMove1d> {
+        let mut moves = Vec::with_capacity(64);
+        let us = color_index(self.curr_player);
+        let them = 1 - us;
+        let occupied = self.occupied();
+        let king = self.king_square(us);
+        let checkers = self.attackers_to(king, occupied) & self.colors[them];
+
+        // The king is taken off the board so it cannot hide from a slider behind itself
+        let without_king = occupied ^ 1 << king;
+        for to in squares(ATTACKS.king(king) & !self.colors[us]) {
+            if self.attackers_to(to, without_king) & self.colors[them] == 0 {
+                self.push_move(&mut moves, king, to);
+            }
+        }
+        // Only the king can get out of a double check
+        if checkers.count_ones() > 1 {
+            return moves;
+        }
+
+        // In check, every other move has to capture the checker or block it
+        let targets = match checkers {
+            0 => !self.colors[us],
+            _ => checkers | ATTACKS.between(king, checkers.trailing_zeros() as usize),
+        };
+        // A pinned piece may only move along the line between the king and the pinner
+        let pinned = self.pinned(us, king, occupied);
+        let pin_line = |from: usize| {
+            if pinned & 1 << from != 0 {
+                ATTACKS.line(king, from)
+            } else {
+                u64::MAX
+            }
+        };
+
+        for from in squares(self.pieces[us][KNIGHT]) {
+            for to in squares(ATTACKS.knight(from) & targets & pin_line(from)) {
+                self.push_move(&mut moves, from, to);
+            }
+        }
+        for from in squares(self.pieces[us][BISHOP]) {
+            for to in squares(ATTACKS.bishop(from, occupied) & targets & pin_line(from)) {
+                self.push_move(&mut moves, from, to);
+            }
+        }
+        for from in squares(self.pieces[us][ROOK]) {
+            for to in squares(ATTACKS.rook(from, occupied) & targets & pin_line(from)) {
+                self.push_move(&mut moves, from, to);
+            }
+        }
+        for from in squares(self.pieces[us][QUEEN]) {
+            for to in squares(ATTACKS.queen(from, occupied) & targets & pin_line(from)) {
+                self.push_move(&mut moves, from, to);
+            }
+        }
+
+        let (forward, double_rank) = match us {
+            0 => (8, RANK_2),
+            _ => (-8, RANK_7),
+        };
+        for from in squares(self.pieces[us][PAWN]) {
+            let allowed = targets & pin_line(from);
+            let one = from.wrapping_add_signed(forward);
+            if occupied & 1 << one == 0 {
+                if allowed & 1 << one != 0 {
+                    self.push_pawn_move(&mut moves, from, one);
+                }
+                let two = one.wrapping_add_signed(forward);
+                if double_rank & 1 << from != 0
+                    && occupied & 1 << two == 0
+                    && allowed & 1 << two != 0
+                {
+                    moves.push(GameMove1d {
+                        start: to_position(from),
+                        end: to_position(two),
+                        passant: Some(PassantTypes::PassantAvailable(to_position(one))),
+                        ..Default::default()
+                    });
+                }
+            }
+            for to in squares(ATTACKS.pawn(us, from) & self.colors[them] & allowed) {
+                self.push_pawn_move(&mut moves, from, to);
+            }
+            if let Some(target) = self.en_passant {
+                if ATTACKS.pawn(us, from) & 1 << target != 0
+                    && self.is_legal_en_passant(from, target, king)
+                {
+                    moves.push(GameMove1d {
+                        start: to_position(from),
+                        end: to_position(target),
+                        passant: Some(PassantTypes::PassantCapture(to_position(
+                            en_passant_victim(from, target),
+                        ))),
+                        capture: true,
+                        ..Default::default()
+                    });
+                }
+            }
+        }
+
+        if checkers == 0 {
+            let rights = self.castling_rights;
+            let castles = match us {
+                0 => [
+                    (rights.white_king, CastleTypes::WhiteKing),
+                    (rights.white_queen, CastleTypes::WhiteQueen),
+                ],
+                _ => [
+                    (rights.black_king, CastleTypes::BlackKing),
+                    (rights.black_queen, CastleTypes::BlackQueen),
+                ],
+            };
+            for (_, castle) in castles.into_iter().filter(|(available, _)| *available) {
+                let (king_from, king_to, rook_from, _) = castle_squares(castle);
+                // Every square the king crosses, including where it lands, must be safe
+                let crossed = ATTACKS.between(king_from, king_to) | 1 << king_to;
+                if king == king_from
+                    && self.pieces[us][ROOK] & 1 << rook_from != 0
+                    && occupied & ATTACKS.between(king_from, rook_from) == 0
+                    && squares(crossed)
+                        .all(|square| self.attackers_to(square, occupied) & self.colors[them] == 0)
+                {
+                    moves.push(GameMove1d {
+                        start: to_position(king_from),
+                        end: to_position(king_to),
+                        castle: Some(castle),
+                        ..Default::default()
+                    });
+                }
+            }
+        }
+
+        moves
+    }
+
+    // Castling and en passant are recognised from the board, so moves parsed from UCI
+    // without any flags play the same
+    fn make_move_in_place(&mut self, mov: &GameMove1d) {
+        let from = square_index(mov.start);
+        let to = square_index(mov.end);
+        let piece = self.squares[from];
+        let pawn_move = piece.piece_type == PieceTypes::Pawn;
+        let captured_square = if pawn_move && Some(to) == self.en_passant {
+            en_passant_victim(from, to)
+        } else {
+            to
+        };
+        let captured = self.squares[captured_square];
+        let castle = match (piece.piece_type, to) {
+            (PieceTypes::King, 6) if from == 4 => Some(CastleTypes::WhiteKing),
+            (PieceTypes::King, 2) if from == 4 => Some(CastleTypes::WhiteQueen),
+            (PieceTypes::King, 62) if from == 60 => Some(CastleTypes::BlackKing),
+            (PieceTypes::King, 58) if from == 60 => Some(CastleTypes::BlackQueen),
+            _ => None,
+        };
+        self.history.push(UndoInfo {
+            from,
+            to,
+            moved: piece,
+            captured,
+            captured_square,
+            castle,
+            castling_rights: self.castling_rights,
+            en_passant: self.en_passant,
+            half_moves: self.half_moves,
+            full_moves: self.full_moves,
+            check: self.check,
+            key: self.key,
+        });
+        self.key ^= ZOBRIST.castle_rights(self.castling_rights);
+        self.key ^= ZOBRIST.en_passant(self.en_passant.map(to_position));
+
+        if captured.piece_type != PieceTypes::Empty {
+            self.remove(captured_square);
+        }
+        self.remove(from);
+        let placed = match mov.promote {
+            Some(promote) => Pieces {
+                piece_type: promote.piece_type,
+                color: piece.color,
+            },
+            None => piece,
+        };
+        self.put(to, placed);
+        if let Some(castle) = castle {
+            let (_, _, rook_from, rook_to) = castle_squares(castle);
+            let rook = self.remove(rook_from);
+            self.put(rook_to, rook);
+        }
+
+        self.en_passant = (pawn_move && from.abs_diff(to) == 16).then_some((from + to) / 2);
+        for square in [from, to] {
+            match square {
+                0 => self.castling_rights.white_queen = false,
+                4 => {
+                    self.castling_rights.white_king = false;
+                    self.castling_rights.white_queen = false;
+                }
+                7 => self.castling_rights.white_king = false,
+                56 => self.castling_rights.black_queen = false,
+                60 => {
+                    self.castling_rights.black_king = false;
+                    self.castling_rights.black_queen = false;
+                }
+                63 => self.castling_rights.black_king = false,
+                _ => {}
+            }
+        }
+        self.key ^= ZOBRIST.castle_rights(self.castling_rights);
+        self.key ^= ZOBRIST.en_passant(self.en_passant.map(to_position));
+
+        if pawn_move || captured.piece_type != PieceTypes::Empty {
+            self.half_moves = 0;
+        } else {
+            self.half_moves = self.half_moves.saturating_add(1);
+        }
+        if self.curr_player == PieceColors::Black {
+            self.full_moves = self.full_moves.saturating_add(1);
+        }
+        self.curr_player = -self.curr_player;
+        self.key ^= ZOBRIST.black_to_move;
+        self.check = self.find_check();
+
+        debug_assert_eq!(
+            self.key,
+            self.compute_key(),
+            "Incremental key diverged after {mov}"
+        );
+    }
+
+    fn make_null_move_in_place(&mut self) {
+        self.history.push(UndoInfo {
+            from: 0,
+            to: 0,
+            moved: EMPTY_PIECE,
+            captured: EMPTY_PIECE,
+            captured_square: 0,
+            castle: None,
+            castling_rights: self.castling_rights,
+            en_passant: self.en_passant,
+            half_moves: self.half_moves,
+            full_moves: self.full_moves,
+            check: self.check,
+            key: self.key,
+        });
+        self.curr_player = -self.curr_player;
+        self.key ^= ZOBRIST.black_to_move;
+        self.key ^= ZOBRIST.en_passant(self.en_passant.map(to_position));
+        self.en_passant = None;
+        self.half_moves = 0;
+        if self.curr_player == PieceColors::White {
+            self.full_moves = self.full_moves.saturating_add(1)
+        }
+        debug_assert_eq!(self.key, self.compute_key());
+    }
+
+    fn unmake_move(&mut self) {
+        let undo = self.history.pop().expect("No move to unmake");
+        if undo.moved.piece_type != PieceTypes::Empty {
+            if let Some(castle) = undo.castle {
+                let (_, _, rook_from, rook_to) = castle_squares(castle);
+                let rook = self.remove(rook_to);
+                self.put(rook_from, rook);
+            }
+            self.remove(undo.to);
+            self.put(undo.from, undo.moved);
+            if undo.captured.piece_type != PieceTypes::Empty {
+                self.put(undo.captured_square, undo.captured);
+            }
+        }
+
+        self.curr_player = -self.curr_player;
+        self.castling_rights = undo.castling_rights;
+        self.en_passant = undo.en_passant;
+        self.half_moves = undo.half_moves;
+        self.full_moves = undo.full_moves;
+        self.check = undo.check;
+        self.key = undo.key;
+    }
+
+    fn get_curr_player(&self) -> PieceColors {
+        self.curr_player
+    }
+
+    fn get_check(&self) -> Option<Checks> {
+        self.check
+    }
+
+    fn get_castle_rights(&self) -> CastleRights {
+        self.castling_rights
+    }
+
+    fn get_en_passant(&self) -> Option<Position> {
+        self.en_passant.map(to_position)
+    }
+
+    fn get_half_moves(&self) -> u16 {
+        self.half_moves
+    }
+
+    fn get_full_moves(&self) -> u16 {
+        self.full_moves
+    }
+
+    fn get_key(&self) -> u64 {
+        self.key
+    }
+
+    fn previous_keys(&self) -> impl Iterator<Item = u64> + '_ {
+        self.history.iter().rev().map(|undo| undo.key)
+    }
+
+    fn piece_at(&self, pos: Position) -> Pieces {
+        self.squares[square_index(pos)]
+    }
+
+    // Same swap list as the `Mailbox`, but the attackers are found with one lookup per
+    // recapture and removing them from `occupied` uncovers the sliders behind
+    fn see(&self, mov: &GameMove1d) -> i32 {
+        if mov.castle.is_some() {
+            return 0;
+        }
+        let from = square_index(mov.start);
+        let target = square_index(mov.end);
+        let mut occupied = self.occupied() ^ 1 << from;
+        let mut gains: Vec<i32> = Vec::with_capacity(32);
+
+        let mut first_gain = self.squares[target].piece_type.value();
+        if let Some(PassantTypes::PassantCapture(pawn)) = mov.passant {
+            first_gain = PieceTypes::Pawn.value();
+            occupied ^= 1 << square_index(pawn);
+        }
+        let mut on_target = self.squares[from].piece_type;
+        if let Some(promote) = mov.promote {
+            first_gain += promote.piece_type.value() - PieceTypes::Pawn.value();
+            on_target = promote.piece_type;
+        }
+        gains.push(first_gain);
+        occupied |= 1 << target;
+
+        let mut side = 1 - color_index(self.curr_player);
+        while let Some((square, piece)) = self.least_valuable_attacker(target, side, occupied) {
+            // The king may only recapture when the square is no longer defended
+            if piece == KING
+                && self
+                    .least_valuable_attacker(target, 1 - side, occupied)
+                    .is_some()
+            {
+                break;
+            }
+            let last_gain = *gains.last().unwrap();
+            gains.push(on_target.value() - last_gain);
+            on_target = PIECE_TYPES[piece];
+            occupied ^= 1 << square;
+            side = 1 - side;
+        }
+
+        // Either side stops capturing as soon as continuing would do worse
+        while gains.len() > 1 {
+            let last_gain = gains.pop().unwrap();
+            let previous = gains.last_mut().unwrap();
+            *previous = -(-*previous).max(last_gain);
+        }
+        gains[0]
+    }
+}
+
+impl Bitboard {
+    // Builds the Zobrist key of the position from scratch, equal to the `Mailbox` key
+    pub fn compute_key(&self) -> u64 {
+        let mut key = 0;
+        for (square, piece) in self.squares.iter().enumerate() {
+            key ^= ZOBRIST.piece(*piece, to_position(square));
+        }
+        if self.curr_player == PieceColors::Black {
+            key ^= ZOBRIST.black_to_move;
+        }
+        key ^= ZOBRIST.castle_rights(self.castling_rights);
+        key ^= ZOBRIST.en_passant(self.en_passant.map(to_position));
+        key
+    }
+
+    fn occupied(&self) -> u64 {
+        self.colors[0] | self.colors[1]
+    }
+
+    fn king_square(&self, color: usize) -> usize {
+        self.pieces[color][KING].trailing_zeros() as usize
+    }
+
+    // Pieces of both colors that attack `square` when the board holds `occupied`
+    fn attackers_to(&self, square: usize, occupied: u64) -> u64 {
+        let [white, black] = self.pieces;
+        let both = |piece: usize| white[piece] | black[piece];
+        // A pawn attacks the squares a pawn of the other color would attack it from
+        (ATTACKS.pawn(1, square) & white[PAWN])
+            | (ATTACKS.pawn(0, square) & black[PAWN])
+            | (ATTACKS.knight(square) & both(KNIGHT))
+            | (ATTACKS.king(square) & both(KING))
+            | (ATTACKS.bishop(square, occupied) & (both(BISHOP) | both(QUEEN)))
+            | (ATTACKS.rook(square, occupied) & (both(ROOK) | both(QUEEN)))
+    }
+
+    // Pieces of `color` that are the only blocker between their king and an enemy slider
+    fn pinned(&self, color: usize, king: usize, occupied: u64) -> u64 {
+        let enemy = self.pieces[1 - color];
+        let snipers = (ATTACKS.bishop(king, 0) & (enemy[BISHOP] | enemy[QUEEN]))
+            | (ATTACKS.rook(king, 0) & (enemy[ROOK] | enemy[QUEEN]));
+        let mut pinned = 0;
+        for sniper in squares(snipers) {
+            let blockers = ATTACKS.between(king, sniper) & occupied;
+            if blockers.count_ones() == 1 {
+                pinned |= blockers & self.colors[color];
+            }
+        }
+        pinned
+    }
+
+    // En passant removes two pawns from the same rank, which can expose the king along it,
+    // so the position after the capture is checked as a whole
+    fn is_legal_en_passant(&self, from: usize, target: usize, king: usize) -> bool {
+        let us = color_index(self.curr_player);
+        let victim = en_passant_victim(from, target);
+        if self.pieces[1 - us][PAWN] & 1 << victim == 0 {
+            return false;
+        }
+        let occupied = self.occupied() ^ 1 << from ^ 1 << victim | 1 << target;
+        self.attackers_to(king, occupied) & self.colors[1 - us] & !(1 << victim) == 0
+    }
+
+    // Lowest valued piece of `color` still on `occupied` that attacks `target`
+    fn least_valuable_attacker(
+        &self,
+        target: usize,
+        color: usize,
+        occupied: u64,
+    ) -> Option<(usize, usize)> {
+        let attackers = self.attackers_to(target, occupied) & occupied;
+        (PAWN..=KING).find_map(|piece| {
+            let found = attackers & self.pieces[color][piece];
+            (found != 0).then_some((found.trailing_zeros() as usize, piece))
+        })
+    }
+
+    fn find_check(&self) -> Option<Checks> {
+        let occupied = self.occupied();
+        let attacked = |color: usize| {
+            self.pieces[color][KING] != 0
+                && self.attackers_to(self.king_square(color), occupied) & self.colors[1 - color]
+                    != 0
+        };
+        if attacked(1) {
+            Some(Checks::Black)
+        } else if attacked(0) {
+            Some(Checks::White)
+        } else {
+            None
+        }
+    }
+
+    fn push_move(&self, moves: &mut Vec<GameMove1d>, from: usize, to: usize) {
+        moves.push(GameMove1d {
+            start: to_position(from),
+            end: to_position(to),
+            capture: self.squares[to].piece_type != PieceTypes::Empty,
+            ..Default::default()
+        });
+    }
+
+    fn push_pawn_move(&self, moves: &mut Vec<GameMove1d>, from: usize, to: usize) {
+        if (RANK_1 | RANK_8) & 1 << to == 0 {
+            self.push_move(moves, from, to);
+            return;
+        }
+        for piece_type in PROMOTABLE_PIECES {
+            moves.push(GameMove1d {
+                start: to_position(from),
+                end: to_position(to),
+                promote: Some(Pieces {
+                    piece_type,
+                    color: self.curr_player,
+                }),
+                capture: self.squares[to].piece_type != PieceTypes::Empty,
+                ..Default::default()
+            });
+        }
+    }
+
+    fn put(&mut self, square: usize, piece: Pieces) {
+        let color = color_index(piece.color);
+        self.pieces[color][piece_index(piece.piece_type)] |= 1 << square;
+        self.colors[color] |= 1 << square;
+        self.squares[square] = piece;
+        self.key ^= ZOBRIST.piece(piece, to_position(square));
+    }
+
+    fn remove(&mut self, square: usize) -> Pieces {
+        let piece = self.squares[square];
+        let color = color_index(piece.color);
+        self.pieces[color][piece_index(piece.piece_type)] &= !(1 << square);
+        self.colors[color] &= !(1 << square);
+        self.squares[square] = EMPTY_PIECE;
+        self.key ^= ZOBRIST.piece(piece, to_position(square));
+        piece
+    }
+}
+
+fn color_index(color: PieceColors) -> usize {
+    match color {
+        PieceColors::Black => 1,
+        _ => 0,
+    }
+}
+
+fn piece_index(piece_type: PieceTypes) -> usize {
+    match piece_type {
+        PieceTypes::Pawn => PAWN,
+        PieceTypes::Knight => KNIGHT,
+        PieceTypes::Bishop => BISHOP,
+        PieceTypes::Rook => ROOK,
+        PieceTypes::Queen => QUEEN,
+        PieceTypes::King => KING,
+        PieceTypes::Empty | PieceTypes::Offboard => unreachable!("No piece to index"),
+    }
+}
+
+// Converts a 0-63 square index into a 10x12 mailbox index
+fn to_position(square: usize) -> Position {
+    Position {
+        value: 21 + square / 8 * 10 + square % 8,
+    }
+}
+
+// The pawn taken en passant stands beside the capturing pawn, on the file it moves to
+fn en_passant_victim(from: usize, target: usize) -> usize {
+    from / 8 * 8 + target % 8
+}
+
+// King start, king end, rook start and rook end of a castling move
+fn castle_squares(castle: CastleTypes) -> (usize, usize, usize, usize) {
+    match castle {
+        CastleTypes::WhiteKing => (4, 6, 7, 5),
+        CastleTypes::WhiteQueen => (4, 2, 0, 3),
+        CastleTypes::BlackKing => (60, 62, 63, 61),
+        CastleTypes::BlackQueen => (60, 58, 56, 59),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    // Standard perft positions with their node counts at depth 1 to 3
+    const PERFT_POSITIONS: [(&str, [usize; 3]); 6] = [
+        (
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            [20, 400, 8902],
+        ),
+        (
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            [48, 2039, 97862],
+        ),
+        ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", [14, 191, 2812]),
+        (
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            [6, 264, 9467],
+        ),
+        (
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            [44, 1486, 62379],
+        ),
+        (
+            "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+            [46, 2079, 89890],
+        ),
+    ];
+
+    fn perft(board: &mut Bitboard, depth: usize) -> usize {
+        let moves = board.get_valid_moves();
+        if depth == 1 {
+            return moves.len();
+        }
+        moves
+            .iter()
+            .map(|mov| {
+                board.make_move_in_place(mov);
+                let nodes = perft(board, depth - 1);
+                board.unmake_move();
+                nodes
+            })
+            .sum()
+    }
+
+    fn mailbox_perft(mailbox: &mut Mailbox, depth: usize) -> usize {
+        let moves = mailbox.get_valid_moves();
+        if depth == 1 {
+            return moves.len();
+        }
+        moves
+            .iter()
+            .map(|mov| {
+                mailbox.make_move_in_place(mov);
+                let nodes = mailbox_perft(mailbox, depth - 1);
+                mailbox.unmake_move();
+                nodes
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_perft_matches_mailbox() {
+        for (fen, counts) in PERFT_POSITIONS {
+            let mut board = Bitboard::setup_board(Some(fen)).unwrap();
+            let mut mailbox = Mailbox::setup_board(Some(fen)).unwrap();
+            for (depth, expected) in (1..).zip(counts) {
+                assert_eq!(perft(&mut board, depth), expected, "{fen} depth {depth}");
+                assert_eq!(
+                    mailbox_perft(&mut mailbox, depth),
+                    expected,
+                    "{fen} depth {depth}"
+                );
+            }
+            assert_eq!(board, Bitboard::setup_board(Some(fen)).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_random_games_match_mailbox() {
+        let sorted = |mut moves: Vec<GameMove1d>| {
+            moves.sort_by_key(|mov| (mov.to_string(), mov.capture, mov.passant.is_some()));
+            moves
+        };
+        let mut rng = StdRng::seed_from_u64(21);
+        for (fen, _) in PERFT_POSITIONS {
+            let mut board = Bitboard::setup_board(Some(fen)).unwrap();
+            let mut mailbox = Mailbox::setup_board(Some(fen)).unwrap();
+            for _ in 0..100 {
+                let moves = sorted(board.get_valid_moves());
+                assert_eq!(moves, sorted(mailbox.get_valid_moves()), "{mailbox}");
+                assert_eq!(board.get_key(), mailbox.get_key());
+                assert_eq!(board.get_check(), mailbox.get_check());
+                assert_eq!(board.half_moves, mailbox.half_moves);
+                let Some(mov) = moves.choose(&mut rng) else {
+                    break;
+                };
+                board.make_move_in_place(mov);
+                mailbox.make_move_in_place(mov);
+            }
+        }
+    }
+}
@@ -0,0 +1,689 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::board::Board;
+use crate::utils::castling::CastleRights;
+use crate::utils::chess_errors::ChessError;
+use crate::utils::gamemove1d::to_str;
+use crate::utils::pieces::{PieceColors, PieceTypes, Pieces};
+use crate::utils::position::Position;
+
+pub const START_POSITION: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+const EMPTY_PIECE: Pieces = Pieces {
+    piece_type: PieceTypes::Empty,
+    color: PieceColors::Empty,
+};
+
+/// A position in Forsyth-Edwards Notation, split into its six fields. Squares are 10x12
+/// mailbox `Position`s like everywhere else.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Fen {
+    // The piece on every square from a1 to h8
+    pub squares: [Pieces; 64],
+    pub curr_player: PieceColors,
+    pub castling_rights: CastleRights,
+    pub en_passant: Option<Position>,
+    pub half_moves: u16,
+    pub full_moves: u16,
+}
+
+impl<B: Board> From<&B> for Fen {
+    fn from(board: &B) -> Self {
+        let mut squares = [EMPTY_PIECE; 64];
+        for (pos, piece) in board.pieces() {
+            squares[(pos.value / 10 - 2) * 8 + pos.value % 10 - 1] = piece;
+        }
+        Fen {
+            squares,
+            curr_player: board.get_curr_player(),
+            castling_rights: board.get_castle_rights(),
+            en_passant: board.get_en_passant(),
+            half_moves: board.get_half_moves(),
+            full_moves: board.get_full_moves(),
+        }
+    }
+}
+
+impl FromStr for Fen {
+    type Err = ChessError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.split_ascii_whitespace().collect();
+        // Move counters are often left out, as in EPD
+        if fields.len() != 6 && fields.len() != 4 {
+            return Err(ChessError::FENFieldCountError {
+                count: fields.len(),
+            });
+        }
+
+        let squares = parse_placement(fields[0])?;
+
+        let curr_player = match fields[1] {
+            "w" => PieceColors::White,
+            "b" => PieceColors::Black,
+            value => {
+                return Err(ChessError::FENSideToMoveError {
+                    value: value.to_string(),
+                })
+            }
+        };
+
+        let castling = fields[2];
+        let rights: Vec<char> = castling.chars().collect();
+        let mut unique = rights.clone();
+        unique.sort();
+        unique.dedup();
+        if castling != "-"
+            && (rights.is_empty()
+                || unique.len() != rights.len()
+                || !rights.iter().all(|right| "KQkq".contains(*right)))
+        {
+            return Err(ChessError::FENCastlingError {
+                value: castling.to_string(),
+            });
+        }
+        let castling_rights = CastleRights {
+            white_king: castling.contains('K'),
+            white_queen: castling.contains('Q'),
+            black_king: castling.contains('k'),
+            black_queen: castling.contains('q'),
+        };
+
+        let en_passant = match fields[3] {
+            "-" => None,
+            value => Some(
+                parse_square(value).ok_or_else(|| ChessError::FENEnPassantError {
+                    value: value.to_string(),
+                })?,
+            ),
+        };
+
+        let half_moves = match fields.get(4) {
+            Some(value) => parse_counter("half move", value, 0)?,
+            None => 0,
+        };
+        let full_moves = match fields.get(5) {
+            Some(value) => parse_counter("full move", value, 1)?,
+            None => 1,
+        };
+
+        let fen = Fen {
+            squares,
+            curr_player,
+            castling_rights,
+            en_passant,
+            half_moves,
+            full_moves,
+        };
+        fen.validate()?;
+        Ok(fen)
+    }
+}
+
+impl fmt::Display for Fen {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut ranks = Vec::with_capacity(8);
+        for rank in self.squares.chunks(8).rev() {
+            let mut placement = String::new();
+            let mut empty = 0;
+            for piece in rank {
+                if piece.piece_type == PieceTypes::Empty {
+                    empty += 1;
+                    continue;
+                }
+                if empty > 0 {
+                    placement.push_str(&empty.to_string());
+                    empty = 0;
+                }
+                placement.push_str(&piece.to_string());
+            }
+            if empty > 0 {
+                placement.push_str(&empty.to_string());
+            }
+            ranks.push(placement);
+        }
+
+        let side = match self.curr_player {
+            PieceColors::Black => "b",
+            _ => "w",
+        };
+
+        let rights = self.castling_rights;
+        let mut castling: String = [
+            (rights.white_king, 'K'),
+            (rights.white_queen, 'Q'),
+            (rights.black_king, 'k'),
+            (rights.black_queen, 'q'),
+        ]
+        .iter()
+        .filter(|(available, _)| *available)
+        .map(|(_, right)| right)
+        .collect();
+        if castling.is_empty() {
+            castling.push('-');
+        }
+
+        let en_passant = match self.en_passant {
+            Some(pos) => to_str(pos.value),
+            None => "-".to_string(),
+        };
+
+        write!(
+            f,
+            "{} {} {} {} {} {}",
+            ranks.join("/"),
+            side,
+            castling,
+            en_passant,
+            self.half_moves,
+            self.full_moves
+        )
+    }
+}
+
+impl Fen {
+    /// Piece standing on a 10x12 mailbox square
+    pub fn piece_at(&self, pos: Position) -> Pieces {
+        self.squares[(pos.value / 10 - 2) * 8 + pos.value % 10 - 1]
+    }
+
+    // Rejects positions that cannot come up in a game, which the move generators all assume
+    fn validate(&self) -> Result<(), ChessError> {
+        for color in [PieceColors::White, PieceColors::Black] {
+            let count = self
+                .squares
+                .iter()
+                .filter(|piece| **piece == king(color))
+                .count();
+            if count != 1 {
+                return Err(ChessError::FENKingCountError {
+                    color: color.to_string().to_lowercase(),
+                    count,
+                });
+            }
+        }
+
+        for square in (0..8).chain(56..64) {
+            if self.squares[square].piece_type == PieceTypes::Pawn {
+                return Err(ChessError::FENPawnRankError {
+                    square: square_name(square),
+                });
+            }
+        }
+
+        // The side to move could capture a king that is left in check
+        let waiting = -self.curr_player;
+        let waiting_king = self
+            .squares
+            .iter()
+            .position(|piece| *piece == king(waiting))
+            .unwrap();
+        if is_attacked(&self.squares, waiting_king, self.curr_player) {
+            return Err(ChessError::FENOpponentInCheckError {
+                color: waiting.to_string().to_lowercase(),
+                square: square_name(waiting_king),
+                side: self.curr_player.to_string().to_lowercase(),
+            });
+        }
+
+        let rights = self.castling_rights;
+        for (available, right, king_square, rook_square, color) in [
+            (rights.white_king, 'K', 4, 7, PieceColors::White),
+            (rights.white_queen, 'Q', 4, 0, PieceColors::White),
+            (rights.black_king, 'k', 60, 63, PieceColors::Black),
+            (rights.black_queen, 'q', 60, 56, PieceColors::Black),
+        ] {
+            let rook = Pieces {
+                piece_type: PieceTypes::Rook,
+                color,
+            };
+            if available
+                && (self.squares[king_square] != king(color) || self.squares[rook_square] != rook)
+            {
+                return Err(ChessError::FENCastlingRightsError {
+                    right,
+                    king: square_name(king_square),
+                    rook: square_name(rook_square),
+                });
+            }
+        }
+
+        // The target is the square a pawn of the side that just moved skipped, so it and the
+        // square the pawn came from are empty, with the pawn right in front of it
+        if let Some(pos) = self.en_passant {
+            let target = (pos.value / 10 - 2) * 8 + pos.value % 10 - 1;
+            let (target_rank, origin, pawn) = match self.curr_player {
+                PieceColors::White => (5, target + 8, target - 8),
+                _ => (2, target - 8, target + 8),
+            };
+            let pawn_moved = Pieces {
+                piece_type: PieceTypes::Pawn,
+                color: waiting,
+            };
+            if target / 8 != target_rank
+                || self.squares[target].piece_type != PieceTypes::Empty
+                || self.squares[origin].piece_type != PieceTypes::Empty
+                || self.squares[pawn] != pawn_moved
+            {
+                return Err(ChessError::FENEnPassantError {
+                    value: to_str(pos.value),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Reads the piece placement field, ranks are listed from the eighth down to the first
+fn parse_placement(placement: &str) -> Result<[Pieces; 64], ChessError> {
+    let mut squares = [EMPTY_PIECE; 64];
+    let ranks: Vec<&str> = placement.split('/').collect();
+    if ranks.len() != 8 {
+        return Err(ChessError::FENRankCountError { count: ranks.len() });
+    }
+    for (row, rank) in ranks.iter().rev().enumerate() {
+        let mut file = 0;
+        for symbol in rank.chars() {
+            match symbol.to_digit(10) {
+                Some(empty) => file += empty as usize,
+                None => {
+                    let piece = Pieces::from(&symbol);
+                    if piece.piece_type == PieceTypes::Empty {
+                        return Err(ChessError::FENPieceError {
+                            symbol,
+                            rank: row + 1,
+                        });
+                    }
+                    if file < 8 {
+                        squares[row * 8 + file] = piece;
+                    }
+                    file += 1;
+                }
+            }
+        }
+        if file != 8 {
+            return Err(ChessError::FENRankLengthError {
+                rank: row + 1,
+                squares: file,
+            });
+        }
+    }
+    Ok(squares)
+}
+
+fn parse_counter(field: &str, value: &str, min: u16) -> Result<u16, ChessError> {
+    value
+        .parse()
+        .ok()
+        .filter(|count| *count >= min)
+        .ok_or_else(|| ChessError::FENMoveCounterError {
+            field: field.to_string(),
+            value: value.to_string(),
+        })
+}
+
+fn king(color: PieceColors) -> Pieces {
+    Pieces {
+        piece_type: PieceTypes::King,
+        color,
+    }
+}
+
+// Name of a 0-63 square, a1 = 0 and h8 = 63
+fn square_name(square: usize) -> String {
+    to_str(21 + square / 8 * 10 + square % 8)
+}
+
+const KNIGHT_STEPS: [(i32, i32); 8] = [
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+];
+const KING_STEPS: [(i32, i32); 8] = [
+    (0, 1),
+    (1, 1),
+    (1, 0),
+    (1, -1),
+    (0, -1),
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+];
+
+// Whether a piece of `color` attacks the 0-63 `square`
+fn is_attacked(squares: &[Pieces; 64], square: usize, color: PieceColors) -> bool {
+    let (file, rank) = ((square % 8) as i32, (square / 8) as i32);
+    let piece_on = |(file_step, rank_step): (i32, i32)| {
+        let (file, rank) = (file + file_step, rank + rank_step);
+        ((0..8).contains(&file) && (0..8).contains(&rank))
+            .then(|| squares[(rank * 8 + file) as usize])
+    };
+    let is_one_of = |piece: Option<Pieces>, types: &[PieceTypes]| {
+        piece.is_some_and(|piece| piece.color == color && types.contains(&piece.piece_type))
+    };
+
+    // A pawn attacks forward, so it stands one rank behind the square from its side
+    let pawn_rank = match color {
+        PieceColors::White => -1,
+        _ => 1,
+    };
+    if [-1, 1]
+        .into_iter()
+        .any(|file_step| is_one_of(piece_on((file_step, pawn_rank)), &[PieceTypes::Pawn]))
+        || KNIGHT_STEPS
+            .into_iter()
+            .any(|step| is_one_of(piece_on(step), &[PieceTypes::Knight]))
+        || KING_STEPS
+            .into_iter()
+            .any(|step| is_one_of(piece_on(step), &[PieceTypes::King]))
+    {
+        return true;
+    }
+
+    KING_STEPS.into_iter().any(|(file_step, rank_step)| {
+        let sliders = if file_step == 0 || rank_step == 0 {
+            [PieceTypes::Rook, PieceTypes::Queen]
+        } else {
+            [PieceTypes::Bishop, PieceTypes::Queen]
+        };
+        let mut distance = 1;
+        while let Some(piece) = piece_on((file_step * distance, rank_step * distance)) {
+            if piece.piece_type != PieceTypes::Empty {
+                return is_one_of(Some(piece), &sliders);
+            }
+            distance += 1;
+        }
+        false
+    })
+}
+
+// Reads a square like "e3", None if it is not on the board
+pub(crate) fn parse_square(square: &str) -> Option<Position> {
+    let mut chars = square.chars();
+    let file = chars.next().filter(|file| ('a'..='h').contains(file))?;
+    let rank = chars.next().filter(|rank| ('1'..='8').contains(rank))?;
+    if chars.next().is_some() {
+        return None;
+    }
+    Some(Position {
+        value: 21 + (file as usize - 'a' as usize) + 10 * (rank as usize - '1' as usize),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::board::array2d::Array2D;
+    use crate::board::bitboard::Bitboard;
+    use crate::board::mailbox::Mailbox;
+
+    const POSITIONS: [&str; 6] = [
+        START_POSITION,
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+    ];
+
+    #[test]
+    fn test_known_positions_round_trip() {
+        for fen in POSITIONS {
+            assert_eq!(Fen::from_str(fen).unwrap().to_string(), fen);
+            assert_eq!(Mailbox::setup_board(Some(fen)).unwrap().to_fen(), fen);
+        }
+    }
+
+    #[test]
+    fn test_fen_after_moves() {
+        let mut game = Mailbox::setup_board(None).unwrap();
+        for uci in ["e2e4", "c7c5", "g1f3"] {
+            let mov = game
+                .get_valid_moves()
+                .into_iter()
+                .find(|mov| mov.to_string() == uci)
+                .unwrap();
+            game.make_move_in_place(&mov);
+            if uci == "e2e4" {
+                assert_eq!(
+                    game.to_fen(),
+                    "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+                );
+            }
+        }
+        assert_eq!(
+            game.to_fen(),
+            "rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"
+        );
+    }
+
+    #[test]
+    fn test_missing_counters_default() {
+        let fen = Fen::from_str("4k3/8/8/8/8/8/8/4K3 b - -").unwrap();
+        assert_eq!(fen.to_string(), "4k3/8/8/8/8/8/8/4K3 b - - 0 1");
+    }
+
+    #[test]
+    fn test_long_games_round_trip() {
+        let fen = "4k3/8/8/8/8/8/8/R3K3 w - - 120 300";
+        assert_eq!(Fen::from_str(fen).unwrap().to_string(), fen);
+        assert_eq!(Mailbox::setup_board(Some(fen)).unwrap().to_fen(), fen);
+        let bitboard = Bitboard::setup_board(Some(fen)).unwrap();
+        assert_eq!(Fen::from(&bitboard).to_string(), fen);
+        let array2d = Array2D::setup_board(Some(fen)).unwrap();
+        assert_eq!(Fen::from(&array2d).to_string(), fen);
+
+        // The full move number keeps counting past 255
+        let mut game = Mailbox::setup_board(Some("4k3/8/8/8/8/8/8/R3K3 b - - 0 255")).unwrap();
+        let mov = game.get_valid_moves()[0];
+        game.make_move_in_place(&mov);
+        assert_eq!(game.get_full_moves(), 256);
+        game.make_null_move_in_place();
+        game.make_null_move_in_place();
+        assert_eq!(game.get_full_moves(), 257);
+    }
+
+    fn check_counters_saturate<B: Board>() {
+        let mut game = B::setup_board(Some("4k3/8/8/8/8/8/8/R3K3 b - - 65535 65535")).unwrap();
+        let mov = game.get_valid_moves()[0];
+        game.make_move_in_place(&mov);
+        assert_eq!(game.get_half_moves(), 65535);
+        assert_eq!(game.get_full_moves(), 65535);
+        game.make_null_move_in_place();
+        game.make_null_move_in_place();
+        assert_eq!(game.get_full_moves(), 65535);
+    }
+
+    #[test]
+    fn test_counters_saturate() {
+        check_counters_saturate::<Mailbox>();
+        check_counters_saturate::<Bitboard>();
+        check_counters_saturate::<Array2D>();
+    }
+
+    fn error(fen: &str) -> String {
+        Fen::from_str(fen).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn test_malformed_fields_are_reported() {
+        assert!(matches!(
+            Fen::from_str(""),
+            Err(ChessError::FENFieldCountError { count: 0 })
+        ));
+        assert!(matches!(
+            Fen::from_str("4k3/8/8/8/8/8/8/4K3 w - - 0"),
+            Err(ChessError::FENFieldCountError { count: 5 })
+        ));
+        assert!(matches!(
+            Fen::from_str("4k3/8/8/8/8/8/4K3 w - - 0 1"),
+            Err(ChessError::FENRankCountError { count: 7 })
+        ));
+        assert!(matches!(
+            Fen::from_str("4k3/8/8/8/8/9/8/4K3 w - - 0 1"),
+            Err(ChessError::FENRankLengthError {
+                rank: 3,
+                squares: 9
+            })
+        ));
+        assert!(matches!(
+            Fen::from_str("4k3/8/8/8/8/8/PPP4/4K3 w - - 0 1"),
+            Err(ChessError::FENRankLengthError {
+                rank: 2,
+                squares: 7
+            })
+        ));
+        assert!(matches!(
+            Fen::from_str("4k3/8/8/3x4/8/8/8/4K3 w - - 0 1"),
+            Err(ChessError::FENPieceError {
+                symbol: 'x',
+                rank: 5
+            })
+        ));
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K3 x - - 0 1"),
+            "Invalid side to move 'x' in FEN, expected w or b"
+        );
+        for castling in ["KQxq", "KK", ""] {
+            let fen = format!("r3k2r/8/8/8/8/8/8/R3K2R w {castling} - 0 1");
+            if castling.is_empty() {
+                assert!(Fen::from_str(&fen).is_err());
+            } else {
+                assert!(matches!(
+                    Fen::from_str(&fen),
+                    Err(ChessError::FENCastlingError { .. })
+                ));
+            }
+        }
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K3 w - e9 0 1"),
+            "Invalid en passant square 'e9' in FEN"
+        );
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K3 w - - x 1"),
+            "Invalid half move counter 'x' in FEN"
+        );
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K3 w - - 0 0"),
+            "Invalid full move counter '0' in FEN"
+        );
+    }
+
+    #[test]
+    fn test_impossible_positions_are_reported() {
+        assert_eq!(
+            error("8/8/8/8/8/8/8/4K3 w - - 0 1"),
+            "FEN has 0 black kings, expected exactly 1"
+        );
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/3KK3 w - - 0 1"),
+            "FEN has 2 white kings, expected exactly 1"
+        );
+        assert_eq!(
+            error("3Pk3/8/8/8/8/8/8/4K3 w - - 0 1"),
+            "FEN has a pawn on d8, pawns cannot stand on the first or last rank"
+        );
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/p3K3 w - - 0 1"),
+            "FEN has a pawn on a1, pawns cannot stand on the first or last rank"
+        );
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/r3K3 b - - 0 1"),
+            "FEN leaves the white king on e1 in check with black to move"
+        );
+        assert!(matches!(
+            Fen::from_str("4k3/8/8/8/8/5n2/8/4K3 b - - 0 1"),
+            Err(ChessError::FENOpponentInCheckError { .. })
+        ));
+        assert!(matches!(
+            Fen::from_str("4k3/3P4/8/8/8/8/8/4K3 w - - 0 1"),
+            Err(ChessError::FENOpponentInCheckError { .. })
+        ));
+        assert!(Fen::from_str("4k3/8/8/8/8/5n2/8/4K3 w - - 0 1").is_ok());
+        assert_eq!(
+            error("r3k2r/8/8/8/8/8/8/R3K1R1 w KQkq - 0 1"),
+            "FEN castling right K needs a king on e1 and a rook on h1"
+        );
+        assert_eq!(
+            error("r3k2r/8/8/8/8/8/8/R4K1R w Q - 0 1"),
+            "FEN castling right Q needs a king on e1 and a rook on a1"
+        );
+        assert!(matches!(
+            Fen::from_str("1r2k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1"),
+            Err(ChessError::FENCastlingRightsError { right: 'q', .. })
+        ));
+        // No black pawn in front of the target, or the wrong rank for the side to move
+        for en_passant in [
+            "4k3/8/8/8/8/8/8/4K3 w - d6 0 1",
+            "4k3/8/8/3p4/8/8/8/4K3 w - d3 0 1",
+            "4k3/3p4/8/3p4/8/8/8/4K3 w - d6 0 1",
+        ] {
+            assert!(
+                matches!(
+                    Fen::from_str(en_passant),
+                    Err(ChessError::FENEnPassantError { .. })
+                ),
+                "{en_passant}"
+            );
+        }
+        assert!(Fen::from_str("4k3/8/8/3p4/8/8/8/4K3 w - d6 0 1").is_ok());
+        assert!(Fen::from_str("4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1").is_ok());
+    }
+
+    #[test]
+    fn test_setup_board_reports_errors() {
+        assert!(Mailbox::setup_board(Some("4k3/8/8/8/8/8/8/4K3 w - -")).is_ok());
+        assert!(matches!(
+            Mailbox::setup_board(Some("4k3/8/8/8/8/8/8/4K3 w")),
+            Err(ChessError::FENFieldCountError { count: 2 })
+        ));
+        assert!(Bitboard::setup_board(Some("8/8/8/8/8/8/8/8 w - - 0 1")).is_err());
+        assert!(Array2D::setup_board(Some("8/8/8/8/8/8/8/8 w - - 0 1")).is_err());
+    }
+
+    // Every position reached must print, parse and set up again as the same position
+    fn assert_round_trips(game: &Mailbox) {
+        let fen = game.to_fen();
+        assert_eq!(Fen::from_str(&fen).unwrap(), Fen::from(game), "{fen}");
+        let restored = Mailbox::setup_board(Some(&fen)).unwrap();
+        assert_eq!(restored.to_fen(), fen);
+        assert_eq!(restored.get_key(), game.get_key(), "{fen}");
+        assert_eq!(restored.get_check(), game.get_check(), "{fen}");
+        assert_eq!(restored.get_valid_moves(), game.get_valid_moves(), "{fen}");
+        for other in [
+            Fen::from(&Bitboard::setup_board(Some(&fen)).unwrap()),
+            Fen::from(&Array2D::setup_board(Some(&fen)).unwrap()),
+        ] {
+            assert_eq!(other.to_string(), fen);
+        }
+    }
+
+    #[test]
+    fn test_random_games_round_trip() {
+        let mut rng = StdRng::seed_from_u64(23);
+        for start in POSITIONS {
+            for _ in 0..4 {
+                let mut game = Mailbox::setup_board(Some(start)).unwrap();
+                for _ in 0..120 {
+                    assert_round_trips(&game);
+                    let moves = game.get_valid_moves();
+                    let Some(mov) = moves.choose(&mut rng) else {
+                        break;
+                    };
+                    game.make_move_in_place(mov);
+                }
+            }
+        }
+    }
+}
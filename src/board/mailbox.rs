@@ -1,14 +1,13 @@
-use std::sync::Arc;
-
+use crate::board::fen::{Fen, START_POSITION};
+use crate::board::Board;
 use crate::structs::attack_maps::AttackMaps;
 use crate::utils::castling::CastleRights;
 use crate::utils::checks::Checks;
 use crate::utils::chess_errors::ChessError;
-use crate::utils::gamemove1d::{to_num, CastleTypes, GameMove1d, PassantTypes};
+use crate::utils::gamemove1d::{CastleTypes, GameMove1d, PassantTypes};
 use crate::utils::pieces::{PieceColors, PieceTypes, Pieces};
 use crate::utils::position::Position;
-
-const START_POSITION: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+use crate::utils::zobrist::ZOBRIST;
 
 const UL: i8 = 9;
 const U: i8 = 10;
@@ -38,19 +37,35 @@ const PROMOTABLE_PIECES: [PieceTypes; 4] = [
     PieceTypes::Knight,
 ];
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Mailbox {
     pub board: [Pieces; 120],
     pub curr_player: PieceColors,
     pub castling_rights: CastleRights,
     pub en_passant: Option<Position>,
-    pub half_moves: u8,
-    pub full_moves: u8,
+    pub half_moves: u16,
+    pub full_moves: u16,
     pub check: Option<Checks>,
     white_king: Position,
     black_king: Position,
-    pub previous_state: Option<Arc<Mailbox>>,
-    pub attack_maps: AttackMaps,
+    // One entry for every move made since the position was set up, most recent last
+    history: Vec<UndoInfo>,
+    key: u64,
+}
+
+// Everything `unmake_move` needs to restore the position before a move
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct UndoInfo {
+    // None for a null move
+    mov: Option<GameMove1d>,
+    moved: Pieces,
+    captured: Pieces,
+    castling_rights: CastleRights,
+    en_passant: Option<Position>,
+    half_moves: u16,
+    full_moves: u16,
+    check: Option<Checks>,
+    key: u64,
 }
 
 impl std::fmt::Display for Mailbox {
@@ -152,71 +167,22 @@ impl std::fmt::Display for Mailbox {
     }
 }
 
-impl Mailbox {
-    pub fn setup_board(fen: Option<&str>) -> Result<Self, ChessError> {
-        let mut fields = fen.unwrap_or(START_POSITION).split_ascii_whitespace();
+impl Board for Mailbox {
+    fn setup_board(fen: Option<&str>) -> Result<Self, ChessError> {
+        let fen: Fen = fen.unwrap_or(START_POSITION).parse()?;
 
         // Read board positions
-        let board_field = fields.next().unwrap();
-        let board = board_field.split('/').rev();
         let mut board_state: [Pieces; 120] = [Pieces {
             piece_type: PieceTypes::Offboard,
             color: PieceColors::Empty,
         }; 120];
-        // Set Board state
-        let mut index: usize = 21;
-        for row in board {
-            for char in row.chars() {
-                match char {
-                    x if x.is_ascii_digit() => {
-                        let num_empty = x.to_digit(10).unwrap() as usize;
-                        for _i in 0..num_empty {
-                            board_state[index] = Pieces {
-                                piece_type: PieceTypes::Empty,
-                                color: PieceColors::Empty,
-                            };
-                            index += 1;
-                        }
-                    }
-                    y => {
-                        board_state[index] = Pieces::from(&y);
-                        index += 1;
-                    }
-                }
-            }
-            index += 2;
+        for index in 0..64 {
+            let pos = Position {
+                value: 21 + index / 8 * 10 + index % 8,
+            };
+            board_state[pos.value] = fen.piece_at(pos);
         }
 
-        // Read Current Move
-        let curr_player = match fields.next().unwrap() {
-            "b" => PieceColors::Black,
-            "w" => PieceColors::White,
-            _ => return Err(ChessError::FENParseError),
-        };
-
-        // Read Castling Rights
-        let temp = fields.next().unwrap();
-        let castling_rights = CastleRights {
-            white_king: temp.contains("K"),
-            white_queen: temp.contains("Q"),
-            black_king: temp.contains("k"),
-            black_queen: temp.contains("q"),
-        };
-
-        // Read En Passant Targets
-        let temp = fields.next().unwrap();
-        let en_passant: Option<Position> = if !temp.eq("-") {
-            Some(Position {
-                value: to_num(temp),
-            })
-        } else {
-            None
-        };
-
-        // Read Move Numbers
-        let half_moves = fields.next().unwrap().parse::<u8>().unwrap();
-        let full_moves = fields.next().unwrap().parse::<u8>().unwrap();
-
         // Find King Positions
         let mut black_king = Position { value: 95 };
         let mut white_king = Position { value: 25 };
@@ -234,28 +200,28 @@ impl Mailbox {
             }
         }
 
-        // Generate Attack Maps
-        let attack_maps = Mailbox::generate_attack_maps(board_state);
-
         // Get Checks
         let check = verify_checks(board_state, white_king, black_king);
 
-        Ok(Mailbox {
+        let mut mailbox = Mailbox {
             board: board_state,
-            curr_player,
-            castling_rights,
-            en_passant,
-            half_moves,
-            full_moves,
+            curr_player: fen.curr_player,
+            castling_rights: fen.castling_rights,
+            en_passant: fen.en_passant,
+            half_moves: fen.half_moves,
+            full_moves: fen.full_moves,
             check,
             white_king,
             black_king,
-            previous_state: None,
-            attack_maps,
-        })
+            history: Vec::new(),
+            key: 0,
+        };
+        mailbox.key = mailbox.compute_key();
+
+        Ok(mailbox)
     }
 
-    pub fn get_valid_moves(&self) -> Vec<GameMove1d> {
+    fn get_valid_moves(&self) -> Vec<GameMove1d> {
         let mut moves = vec![];
         for (i, piece) in self.board.iter().enumerate() {
             let pos = Position { value: i };
@@ -298,162 +264,281 @@ impl Mailbox {
         moves
     }
 
-    pub fn make_move(&self, mov: &GameMove1d) -> Self {
-        let mut new_mailbox = self.clone();
-        let mut irreversible = false;
-        let piece = new_mailbox.board[mov.start.value];
-        new_mailbox.board[mov.start.value] = EMPTY_PIECE;
-        new_mailbox.board[mov.end.value] = piece;
-        new_mailbox.curr_player = -new_mailbox.curr_player;
+    fn make_move_in_place(&mut self, mov: &GameMove1d) {
+        let piece = self.board[mov.start.value];
+        let captured_pos = match mov.passant {
+            Some(PassantTypes::PassantCapture(pos)) => pos,
+            _ => mov.end,
+        };
+        let captured = self.board[captured_pos.value];
+        self.history.push(UndoInfo {
+            mov: Some(*mov),
+            moved: piece,
+            captured,
+            castling_rights: self.castling_rights,
+            en_passant: self.en_passant,
+            half_moves: self.half_moves,
+            full_moves: self.full_moves,
+            check: self.check,
+            key: self.key,
+        });
+        let old_rights = self.castling_rights;
+
+        // Move the piece, removing whatever it captured
+        self.key ^= ZOBRIST.piece(captured, captured_pos);
+        self.board[captured_pos.value] = EMPTY_PIECE;
+        self.key ^= ZOBRIST.piece(piece, mov.start);
+        self.board[mov.start.value] = EMPTY_PIECE;
+        let placed = mov.promote.unwrap_or(piece);
+        self.key ^= ZOBRIST.piece(placed, mov.end);
+        self.board[mov.end.value] = placed;
+        self.curr_player = -self.curr_player;
+        self.key ^= ZOBRIST.black_to_move;
 
         // Check if it was a castle, and move rook accordingly
         if let Some(castle_type) = mov.castle {
-            irreversible = true;
-            match castle_type {
-                CastleTypes::WhiteKing => {
-                    let temp = new_mailbox.board[28];
-                    new_mailbox.board[28] = EMPTY_PIECE;
-                    new_mailbox.board[26] = temp;
-                }
-                CastleTypes::WhiteQueen => {
-                    let temp = new_mailbox.board[21];
-                    new_mailbox.board[21] = EMPTY_PIECE;
-                    new_mailbox.board[25] = temp;
-                }
-                CastleTypes::BlackKing => {
-                    let temp = new_mailbox.board[98];
-                    new_mailbox.board[98] = EMPTY_PIECE;
-                    new_mailbox.board[96] = temp;
-                }
-                CastleTypes::BlackQueen => {
-                    let temp = new_mailbox.board[91];
-                    new_mailbox.board[91] = EMPTY_PIECE;
-                    new_mailbox.board[95] = temp;
-                }
-            }
+            let (rook_start, rook_end) = castle_rook_squares(castle_type);
+            let rook = self.board[rook_start.value];
+            self.board[rook_start.value] = EMPTY_PIECE;
+            self.board[rook_end.value] = rook;
+            self.key ^= ZOBRIST.piece(rook, rook_start);
+            self.key ^= ZOBRIST.piece(rook, rook_end);
         };
 
         // Reset passant move if previous state had one
-        new_mailbox.en_passant = None;
-
-        // Check if it was a passant move and correct the board accordingly
-        if let Some(passant_type) = mov.passant {
-            irreversible = true;
-            match passant_type {
-                PassantTypes::PassantCapture(pos) => new_mailbox.board[pos.value] = EMPTY_PIECE,
-                PassantTypes::PassantAvailable(pos) => new_mailbox.en_passant = Some(pos),
-            }
-        };
-
-        // Check if promotion and change accordingly
-        if let Some(promotion) = mov.promote {
-            irreversible = true;
-            new_mailbox.board[mov.end.value] = promotion;
-        };
+        self.key ^= ZOBRIST.en_passant(self.en_passant);
+        self.en_passant = None;
+        if let Some(PassantTypes::PassantAvailable(pos)) = mov.passant {
+            self.key ^= ZOBRIST.en_passant(Some(pos));
+            self.en_passant = Some(pos);
+        }
 
         // Update King position if king moved
         match piece {
             Pieces {
                 piece_type: PieceTypes::King,
                 color: PieceColors::White,
-            } => new_mailbox.white_king = mov.end,
+            } => self.white_king = mov.end,
             Pieces {
                 piece_type: PieceTypes::King,
                 color: PieceColors::Black,
-            } => new_mailbox.black_king = mov.end,
+            } => self.black_king = mov.end,
             Pieces { .. } => {}
         }
 
-        // Update castling rights
-        match piece {
-            Pieces {
-                piece_type: PieceTypes::King,
-                color: PieceColors::White,
-            } => {
-                if self.castling_rights.white_king || self.castling_rights.white_queen {
-                    irreversible = true;
-                    new_mailbox.castling_rights.white_king = false;
-                    new_mailbox.castling_rights.white_queen = false;
+        // Update castling rights, which are lost once the king or rook leaves its starting
+        // square or the rook is captured there
+        for square in [mov.start.value, mov.end.value] {
+            match square {
+                21 => self.castling_rights.white_queen = false,
+                25 => {
+                    self.castling_rights.white_king = false;
+                    self.castling_rights.white_queen = false;
                 }
-            }
-            Pieces {
-                piece_type: PieceTypes::King,
-                color: PieceColors::Black,
-            } => {
-                if self.castling_rights.black_king || self.castling_rights.black_queen {
-                    irreversible = true;
-                    new_mailbox.castling_rights.black_king = false;
-                    new_mailbox.castling_rights.black_queen = false;
+                28 => self.castling_rights.white_king = false,
+                91 => self.castling_rights.black_queen = false,
+                95 => {
+                    self.castling_rights.black_king = false;
+                    self.castling_rights.black_queen = false;
                 }
+                98 => self.castling_rights.black_king = false,
+                _ => {}
             }
-            Pieces {
-                piece_type: PieceTypes::Rook,
-                color: PieceColors::White,
-            } => {
-                if self.castling_rights.white_king && mov.start.value == 28 {
-                    irreversible = false;
-                    new_mailbox.castling_rights.white_king = false;
-                }
-                if self.castling_rights.white_queen && mov.start.value == 21 {
-                    irreversible = false;
-                    new_mailbox.castling_rights.white_queen = false;
-                }
-            }
-            Pieces {
-                piece_type: PieceTypes::Rook,
-                color: PieceColors::Black,
-            } => {
-                if self.castling_rights.black_king && mov.start.value == 98 {
-                    irreversible = false;
-                    new_mailbox.castling_rights.black_king = false;
-                }
-                if self.castling_rights.black_queen && mov.start.value == 91 {
-                    irreversible = false;
-                    new_mailbox.castling_rights.black_queen = false;
-                }
-            }
-            _ => {}
         }
 
-        // Update half moves
-        if irreversible || piece.piece_type == PieceTypes::Pawn {
-            new_mailbox.half_moves = 0;
+        self.key ^= ZOBRIST.castle_rights(old_rights);
+        self.key ^= ZOBRIST.castle_rights(self.castling_rights);
+
+        // Update half moves, only pawn moves and captures reset the clock
+        if captured.piece_type != PieceTypes::Empty || piece.piece_type == PieceTypes::Pawn {
+            self.half_moves = 0;
         } else {
-            new_mailbox.half_moves += 1;
+            self.half_moves = self.half_moves.saturating_add(1);
         }
         // Update full moves
-        if new_mailbox.curr_player == PieceColors::White {
-            new_mailbox.full_moves += 1
+        if self.curr_player == PieceColors::White {
+            self.full_moves = self.full_moves.saturating_add(1)
         }
 
-        // Generate Attack Maps
-        new_mailbox.attack_maps = Mailbox::generate_attack_maps(new_mailbox.board);
-
-        // Add link to current board state
-        new_mailbox.previous_state = Some(Arc::new(self.clone()));
-
         // Update who is in check
-        new_mailbox.check = verify_checks(
-            new_mailbox.board,
-            new_mailbox.white_king,
-            new_mailbox.black_king,
+        self.check = verify_checks(self.board, self.white_king, self.black_king);
+
+        debug_assert_eq!(
+            self.key,
+            self.compute_key(),
+            "Incremental key diverged after {mov}"
         );
+    }
 
-        new_mailbox
+    fn unmake_move(&mut self) {
+        let undo = self.history.pop().expect("No move to unmake");
+        self.curr_player = -self.curr_player;
+        self.castling_rights = undo.castling_rights;
+        self.en_passant = undo.en_passant;
+        self.half_moves = undo.half_moves;
+        self.full_moves = undo.full_moves;
+        self.check = undo.check;
+        self.key = undo.key;
+
+        let Some(mov) = undo.mov else {
+            return;
+        };
+        // Pieces go back in the reverse order they were moved in
+        if let Some(castle_type) = mov.castle {
+            let (rook_start, rook_end) = castle_rook_squares(castle_type);
+            self.board[rook_start.value] = self.board[rook_end.value];
+            self.board[rook_end.value] = EMPTY_PIECE;
+        }
+        self.board[mov.end.value] = EMPTY_PIECE;
+        match mov.passant {
+            Some(PassantTypes::PassantCapture(pos)) => self.board[pos.value] = undo.captured,
+            _ => self.board[mov.end.value] = undo.captured,
+        }
+        self.board[mov.start.value] = undo.moved;
+        match undo.moved {
+            Pieces {
+                piece_type: PieceTypes::King,
+                color: PieceColors::White,
+            } => self.white_king = mov.start,
+            Pieces {
+                piece_type: PieceTypes::King,
+                color: PieceColors::Black,
+            } => self.black_king = mov.start,
+            Pieces { .. } => {}
+        }
+    }
+
+    fn make_null_move_in_place(&mut self) {
+        self.history.push(UndoInfo {
+            mov: None,
+            moved: EMPTY_PIECE,
+            captured: EMPTY_PIECE,
+            castling_rights: self.castling_rights,
+            en_passant: self.en_passant,
+            half_moves: self.half_moves,
+            full_moves: self.full_moves,
+            check: self.check,
+            key: self.key,
+        });
+        self.curr_player = -self.curr_player;
+        self.key ^= ZOBRIST.black_to_move;
+        self.key ^= ZOBRIST.en_passant(self.en_passant);
+        self.en_passant = None;
+        self.half_moves = 0;
+        if self.curr_player == PieceColors::White {
+            self.full_moves = self.full_moves.saturating_add(1)
+        }
+        debug_assert_eq!(self.key, self.compute_key());
     }
 
-    pub fn get_check(&self) -> Option<Checks> {
+    fn previous_keys(&self) -> impl Iterator<Item = u64> + '_ {
+        self.history.iter().rev().map(|undo| undo.key)
+    }
+
+    fn get_check(&self) -> Option<Checks> {
         self.check
     }
 
-    pub fn get_curr_player(&self) -> PieceColors {
+    fn get_curr_player(&self) -> PieceColors {
         self.curr_player
     }
 
-    pub fn get_castle_rights(&self) -> CastleRights {
+    fn get_castle_rights(&self) -> CastleRights {
         self.castling_rights
     }
 
+    fn get_en_passant(&self) -> Option<Position> {
+        self.en_passant
+    }
+
+    fn get_half_moves(&self) -> u16 {
+        self.half_moves
+    }
+
+    fn get_full_moves(&self) -> u16 {
+        self.full_moves
+    }
+
+    fn get_key(&self) -> u64 {
+        self.key
+    }
+
+    fn piece_at(&self, pos: Position) -> Pieces {
+        self.board[pos.value]
+    }
+
+    /// Static exchange evaluation: the material the side to move wins with `mov` if both
+    /// sides keep recapturing on the target square with their least valuable attacker and
+    /// may stop whenever recapturing would lose material
+    fn see(&self, mov: &GameMove1d) -> i32 {
+        if mov.castle.is_some() {
+            return 0;
+        }
+        let target = mov.end;
+        let mut board = self.board;
+        let mut gains: Vec<i32> = Vec::with_capacity(32);
+
+        let mut first_gain = board[target.value].piece_type.value();
+        if let Some(PassantTypes::PassantCapture(pawn)) = mov.passant {
+            first_gain = PieceTypes::Pawn.value();
+            board[pawn.value] = EMPTY_PIECE;
+        }
+        // A promotion replaces the pawn on the target square with the new piece
+        let mut on_target = board[mov.start.value];
+        if let Some(promote) = mov.promote {
+            first_gain += promote.piece_type.value() - PieceTypes::Pawn.value();
+            on_target.piece_type = promote.piece_type;
+        }
+        gains.push(first_gain);
+        board[target.value] = on_target;
+        board[mov.start.value] = EMPTY_PIECE;
+
+        // Removing every attacker from the board uncovers the sliders behind it
+        let mut side = -self.curr_player;
+        while let Some(attacker) = least_valuable_attacker(&board, target, side) {
+            // The king may only recapture when the square is no longer defended
+            if board[attacker.value].piece_type == PieceTypes::King
+                && least_valuable_attacker(&board, target, -side).is_some()
+            {
+                break;
+            }
+            let last_gain = *gains.last().unwrap();
+            gains.push(board[target.value].piece_type.value() - last_gain);
+            board[target.value] = board[attacker.value];
+            board[attacker.value] = EMPTY_PIECE;
+            side = -side;
+        }
+
+        // Either side stops capturing as soon as continuing would do worse
+        while gains.len() > 1 {
+            let last_gain = gains.pop().unwrap();
+            let previous = gains.last_mut().unwrap();
+            *previous = -(-*previous).max(last_gain);
+        }
+        gains[0]
+    }
+}
+
+impl Mailbox {
+    /// The position in Forsyth-Edwards Notation
+    pub fn to_fen(&self) -> String {
+        Fen::from(self).to_string()
+    }
+
+    // Builds the Zobrist key of the position from scratch
+    pub fn compute_key(&self) -> u64 {
+        let mut key = 0;
+        for (index, piece) in self.board.iter().enumerate() {
+            key ^= ZOBRIST.piece(*piece, Position { value: index });
+        }
+        if self.curr_player == PieceColors::Black {
+            key ^= ZOBRIST.black_to_move;
+        }
+        key ^= ZOBRIST.castle_rights(self.castling_rights);
+        key ^= ZOBRIST.en_passant(self.en_passant);
+        key
+    }
+
     fn is_legal_square(board: [Pieces; 120], pos: Position) -> bool {
         board[pos.value].piece_type != PieceTypes::Offboard
     }
@@ -570,7 +655,8 @@ impl Mailbox {
                         castle: Some(CastleTypes::WhiteQueen),
                         ..Default::default()
                     })
-                } else if self.castling_rights.white_king
+                }
+                if self.castling_rights.white_king
                     // && self.attack_maps.black[26..=27].iter().all(|&x| x == 0)
                     && self.board[26..=27]
                         .iter()
@@ -601,7 +687,8 @@ impl Mailbox {
                         castle: Some(CastleTypes::BlackQueen),
                         ..Default::default()
                     })
-                } else if self.castling_rights.black_king
+                }
+                if self.castling_rights.black_king
                     // && self.attack_maps.white[96..=97].iter().all(|&x| x == 0)
                     && self.board[96..=97]
                         .iter()
@@ -797,6 +884,10 @@ impl Mailbox {
             }
         }
         let mut test_board = self.board;
+        // An en passant capture also removes the pawn beside the start square
+        if test_board[start.value].piece_type == PieceTypes::Pawn && Some(*end) == self.en_passant {
+            test_board[start.value / 10 * 10 + end.value % 10] = EMPTY_PIECE;
+        }
         test_board[end.value] = test_board[start.value];
         test_board[start.value] = EMPTY_PIECE;
 
@@ -943,24 +1034,20 @@ impl Mailbox {
         }
     }
 
-    pub fn get_prev(&self) -> Option<Arc<Mailbox>> {
-        self.previous_state.as_ref().cloned()
-    }
-
     pub fn get_attack_maps(&self) -> AttackMaps {
-        self.attack_maps
-    }
-
-    fn backtrace(&self) {
-        self.print_info();
-        if let Some(ref prev) = self.previous_state {
-            prev.backtrace();
-        }
+        Mailbox::generate_attack_maps(self.board)
     }
+}
 
-    fn print_info(&self) {
-        todo!();
-    }
+// Start and end square of the rook in a castling move
+fn castle_rook_squares(castle_type: CastleTypes) -> (Position, Position) {
+    let (start, end) = match castle_type {
+        CastleTypes::WhiteKing => (28, 26),
+        CastleTypes::WhiteQueen => (21, 24),
+        CastleTypes::BlackKing => (98, 96),
+        CastleTypes::BlackQueen => (91, 94),
+    };
+    (Position { value: start }, Position { value: end })
 }
 
 fn verify_checks(
@@ -1222,10 +1309,248 @@ fn is_black_checked(board: [Pieces; 120], black_king: Position) -> bool {
     false
 }
 
+// Lowest valued piece of `color` attacking `target` on `board`
+fn least_valuable_attacker(
+    board: &[Pieces; 120],
+    target: Position,
+    color: PieceColors,
+) -> Option<Position> {
+    let step = |from: Position, offset: i8| Position {
+        value: from
+            .value
+            .checked_add_signed(offset as isize)
+            .expect("Invalid position found"),
+    };
+    let is_piece =
+        |pos: Position, piece_type: PieceTypes| board[pos.value] == Pieces { piece_type, color };
+    let mut attackers: Vec<Position> = Vec::new();
+
+    let pawn_offsets = match color {
+        PieceColors::White => [DL, DR],
+        _ => [UL, UR],
+    };
+    for offset in pawn_offsets {
+        let pos = step(target, offset);
+        if is_piece(pos, PieceTypes::Pawn) {
+            attackers.push(pos);
+        }
+    }
+    for offset in KNIGHT_OFFSETS {
+        let pos = step(target, offset);
+        if is_piece(pos, PieceTypes::Knight) {
+            attackers.push(pos);
+        }
+    }
+    for (offsets, slider) in [
+        (BISHOP_OFFSETS, PieceTypes::Bishop),
+        (ROOK_OFFSETS, PieceTypes::Rook),
+    ] {
+        for offset in offsets {
+            let mut pos = step(target, offset);
+            while board[pos.value].piece_type == PieceTypes::Empty {
+                pos = step(pos, offset);
+            }
+            if is_piece(pos, slider) || is_piece(pos, PieceTypes::Queen) {
+                attackers.push(pos);
+            }
+        }
+    }
+    for offset in QUEEN_OFFSETS {
+        let pos = step(target, offset);
+        if is_piece(pos, PieceTypes::King) {
+            attackers.push(pos);
+        }
+    }
+
+    attackers
+        .into_iter()
+        .min_by_key(|pos| board[pos.value].piece_type.value())
+}
+
 fn can_promote(test_pos: Position, curr_player: PieceColors) -> bool {
     ((21..=28).contains(&test_pos.value) && curr_player == PieceColors::Black)
         || ((91..=98).contains(&test_pos.value) && curr_player == PieceColors::White)
 }
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+
+    fn play(game: &Mailbox, moves: &[&str]) -> Mailbox {
+        moves.iter().fold(game.clone(), |game, mov| {
+            let mov = game
+                .get_valid_moves()
+                .into_iter()
+                .find(|x| x.to_string() == *mov)
+                .unwrap();
+            game.make_move(&mov)
+        })
+    }
+
+    #[test]
+    fn test_key_transpositions() {
+        let game = Mailbox::setup_board(None).unwrap();
+        let first = play(&game, &["g1f3", "g8f6", "b1c3"]);
+        let second = play(&game, &["b1c3", "g8f6", "g1f3"]);
+        assert_eq!(first.get_key(), second.get_key());
+
+        let back_home = play(&game, &["g1f3", "g8f6", "f3g1", "f6g8"]);
+        assert_eq!(back_home.get_key(), game.get_key());
+
+        // Same pieces, different side to move
+        let black_to_move = Mailbox::setup_board(Some(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1",
+        ))
+        .unwrap();
+        assert_ne!(black_to_move.get_key(), game.get_key());
+    }
+
+    #[test]
+    fn test_key_matches_fen_setup() {
+        let game = Mailbox::setup_board(None).unwrap();
+        let played = play(&game, &["e2e4", "c7c5", "g1f3"]);
+        let from_fen = Mailbox::setup_board(Some(
+            "rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2",
+        ))
+        .unwrap();
+        assert_eq!(played.get_key(), from_fen.get_key());
+    }
+
+    #[test]
+    fn test_null_move_passes_the_turn() {
+        let game = play(&Mailbox::setup_board(None).unwrap(), &["e2e4"]);
+        let null = game.make_null_move();
+        assert_eq!(null.get_curr_player(), PieceColors::White);
+        assert_eq!(null.en_passant, None);
+        assert_eq!(null.get_key(), null.compute_key());
+        assert_ne!(null.get_key(), game.get_key());
+        assert_eq!(
+            null.make_null_move().get_key(),
+            game.compute_key() ^ ZOBRIST.en_passant(game.en_passant)
+        );
+    }
+
+    #[test]
+    fn test_unmake_restores_position() {
+        use rand::rngs::StdRng;
+        use rand::seq::SliceRandom;
+        use rand::SeedableRng;
+
+        // Castling both ways, en passant and promotions are all reachable from here
+        let start = Mailbox::setup_board(Some(
+            "r3k2r/pPppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPpP/R3K2R w KQkq - 0 1",
+        ))
+        .unwrap();
+        let mut rng = StdRng::seed_from_u64(20);
+        for _ in 0..20 {
+            let mut game = start.clone();
+            let mut positions = vec![game.clone()];
+            for _ in 0..40 {
+                let Some(mov) = game.get_valid_moves().choose(&mut rng).copied() else {
+                    break;
+                };
+                game.make_move_in_place(&mov);
+                assert_eq!(game, positions.last().unwrap().make_move(&mov));
+                positions.push(game.clone());
+            }
+            while positions.len() > 1 {
+                positions.pop();
+                game.unmake_move();
+                assert_eq!(&game, positions.last().unwrap());
+            }
+        }
+    }
+
+    fn find_move(game: &Mailbox, mov: &str) -> GameMove1d {
+        game.get_valid_moves()
+            .into_iter()
+            .find(|x| x.to_string() == mov)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_see_resolves_exchanges() {
+        // A defended pawn costs the rook, unless a second rook x-rays through the first
+        let single = Mailbox::setup_board(Some("4r1k1/8/8/4p3/8/8/4R3/5K2 w - - 0 1")).unwrap();
+        assert_eq!(single.see(&find_move(&single, "e2e5")), 100 - 500);
+        let doubled = Mailbox::setup_board(Some("4r1k1/8/8/4p3/8/8/4R3/4RK2 w - - 0 1")).unwrap();
+        assert_eq!(doubled.see(&find_move(&doubled, "e2e5")), 100);
+
+        // The king may not recapture a piece that is still defended
+        let guarded = Mailbox::setup_board(Some("2k5/3p4/8/8/8/8/3Q4/3RK3 w - - 0 1")).unwrap();
+        assert_eq!(guarded.see(&find_move(&guarded, "d2d7")), 100);
+        let hanging = Mailbox::setup_board(Some("2k5/3p4/8/8/8/8/3Q4/4K3 w - - 0 1")).unwrap();
+        assert_eq!(hanging.see(&find_move(&hanging, "d2d7")), 100 - 900);
+
+        // En passant removes the pawn beside the target square
+        let passant = Mailbox::setup_board(Some("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1")).unwrap();
+        assert_eq!(passant.see(&find_move(&passant, "e5d6")), 100);
+    }
+
+    #[test]
+    fn test_see_ge_thresholds() {
+        let game = Mailbox::setup_board(Some("4k3/8/2p5/3n4/8/4N3/8/Q3K3 w - - 0 1")).unwrap();
+        // Winning the knight costs a knight back: even trade
+        let capture = find_move(&game, "e3d5");
+        assert!(game.see_ge(&capture, 0));
+        assert!(!game.see_ge(&capture, 1));
+        // A quiet move onto a square the knight attacks hangs the queen
+        let quiet = find_move(&game, "a1c3");
+        assert!(!game.see_ge(&quiet, 0));
+        assert!(game.see_ge(&find_move(&game, "a1a4"), 0));
+    }
+
+    #[test]
+    fn test_castles_both_sides() {
+        let game = Mailbox::setup_board(Some("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1")).unwrap();
+        find_move(&game, "e1g1");
+        find_move(&game, "e1c1");
+        let game = play(&game, &["a1b1"]);
+        find_move(&game, "e8g8");
+        find_move(&game, "e8c8");
+    }
+
+    #[test]
+    fn test_queen_side_castle_moves_rook_to_d_file() {
+        for (fen, mov, rook, king, empty) in [
+            ("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "e1c1", 24, 23, 21),
+            ("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1", "e8c8", 94, 93, 91),
+        ] {
+            let game = play(&Mailbox::setup_board(Some(fen)).unwrap(), &[mov]);
+            assert_eq!(game.board[rook].piece_type, PieceTypes::Rook);
+            assert_eq!(game.board[king].piece_type, PieceTypes::King);
+            assert_eq!(game.board[empty].piece_type, PieceTypes::Empty);
+        }
+    }
+
+    #[test]
+    fn test_en_passant_cannot_expose_king() {
+        // Both pawns leave the fifth rank, opening it for the rook
+        let pinned = Mailbox::setup_board(Some("8/8/8/KPp4r/8/8/8/7k w - c6 0 1")).unwrap();
+        assert!(pinned
+            .get_valid_moves()
+            .iter()
+            .all(|x| x.to_string() != "b5c6"));
+        let free = Mailbox::setup_board(Some("8/8/8/1Pp4r/K7/8/8/7k w - c6 0 1")).unwrap();
+        find_move(&free, "b5c6");
+    }
+
+    #[test]
+    fn test_half_move_clock() {
+        let game = Mailbox::setup_board(Some("r3k2r/p7/8/8/8/8/8/R3K2R w KQkq - 5 1")).unwrap();
+        // Castling and moves that lose castling rights do not reset the clock
+        assert_eq!(play(&game, &["e1g1"]).half_moves, 6);
+        assert_eq!(play(&game, &["h1h2", "e8f8"]).half_moves, 7);
+        assert_eq!(play(&game, &["a1a7"]).half_moves, 0);
+        assert_eq!(play(&game, &["e1f1", "a7a6"]).half_moves, 0);
+    }
+
+    #[test]
+    fn test_captured_rook_loses_castling_rights() {
+        let game = Mailbox::setup_board(Some("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1")).unwrap();
+        let game = play(&game, &["h1h8"]);
+        let rights = game.get_castle_rights();
+        assert!(!rights.white_king && !rights.black_king);
+        assert!(rights.white_queen && rights.black_queen);
+    }
+}
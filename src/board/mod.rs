@@ -1,2 +1,262 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::utils::castling::CastleRights;
+use crate::utils::checks::Checks;
+use crate::utils::chess_errors::ChessError;
+use crate::utils::gamemove1d::{GameMove1d, PassantTypes};
+use crate::utils::pieces::{PieceColors, PieceTypes, Pieces};
+use crate::utils::position::Position;
+
 pub mod array2d;
+pub mod bitboard;
+pub mod fen;
 pub mod mailbox;
+pub mod san;
+
+/// State of a game from the point of view of the side to move
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameStatus {
+    Ongoing,
+    Checkmate,
+    Stalemate,
+    FiftyMoveRule,
+    Repetition,
+}
+
+/// A chess position the searches, perft and the CLI can work with, whatever its
+/// representation. Squares are always 10x12 mailbox `Position`s and moves `GameMove1d`s.
+pub trait Board: Clone + Display + Send + Sync {
+    /// Sets up the position of a FEN string, or the starting position
+    fn setup_board(fen: Option<&str>) -> Result<Self, ChessError>;
+
+    fn get_valid_moves(&self) -> Vec<GameMove1d>;
+
+    /// Plays `mov` on this board, `unmake_move` takes it back
+    fn make_move_in_place(&mut self, mov: &GameMove1d);
+
+    /// Passes the turn to the opponent without moving, which is only legal for the search.
+    /// Positions before the null move never count as repetitions.
+    fn make_null_move_in_place(&mut self);
+
+    /// Takes back the last move or null move made in place
+    fn unmake_move(&mut self);
+
+    fn get_curr_player(&self) -> PieceColors;
+
+    fn get_check(&self) -> Option<Checks>;
+
+    fn get_castle_rights(&self) -> CastleRights;
+
+    fn get_en_passant(&self) -> Option<Position>;
+
+    /// Plies since the last pawn move or capture
+    fn get_half_moves(&self) -> u16;
+
+    fn get_full_moves(&self) -> u16;
+
+    fn get_key(&self) -> u64;
+
+    /// Keys of the positions before this one, most recent first
+    fn previous_keys(&self) -> impl Iterator<Item = u64> + '_;
+
+    fn piece_at(&self, pos: Position) -> Pieces;
+
+    /// Returns the position after `mov`, leaving this one untouched
+    fn make_move(&self, mov: &GameMove1d) -> Self {
+        let mut new_board = self.clone();
+        new_board.make_move_in_place(mov);
+        new_board
+    }
+
+    fn make_null_move(&self) -> Self {
+        let mut new_board = self.clone();
+        new_board.make_null_move_in_place();
+        new_board
+    }
+
+    /// Every piece on the board with its square, from a1 to h8
+    fn pieces(&self) -> impl Iterator<Item = (Position, Pieces)> + '_ {
+        (0..64)
+            .map(|square| Position {
+                value: 21 + square / 8 * 10 + square % 8,
+            })
+            .map(|pos| (pos, self.piece_at(pos)))
+            .filter(|(_, piece)| piece.piece_type != PieceTypes::Empty)
+    }
+
+    /// Material the side to move wins with `mov` once all exchanges on its end square are
+    /// resolved. Without a board specific exchange evaluation every capture is assumed to
+    /// win the captured piece.
+    fn see(&self, mov: &GameMove1d) -> i32 {
+        let captured = match mov.passant {
+            Some(PassantTypes::PassantCapture(_)) => PieceTypes::Pawn,
+            _ => self.piece_at(mov.end).piece_type,
+        };
+        captured.value()
+    }
+
+    /// Whether `mov` wins at least `threshold` once the exchange is over
+    fn see_ge(&self, mov: &GameMove1d, threshold: i32) -> bool {
+        self.see(mov) >= threshold
+    }
+
+    /// Whether the position occurred twice before, only positions since the last
+    /// irreversible move can repeat
+    fn is_threefold_repetition(&self) -> bool {
+        let key = self.get_key();
+        let repetitions = (1..=self.get_half_moves())
+            .zip(self.previous_keys())
+            .filter(|(plies_back, previous)| plies_back % 2 == 0 && *previous == key)
+            .count();
+        repetitions >= 2
+    }
+
+    fn game_status(&self) -> GameStatus {
+        if self.get_valid_moves().is_empty() {
+            return match self.get_check() {
+                Some(_) => GameStatus::Checkmate,
+                None => GameStatus::Stalemate,
+            };
+        }
+        if self.get_half_moves() >= 100 {
+            GameStatus::FiftyMoveRule
+        } else if self.is_threefold_repetition() {
+            GameStatus::Repetition
+        } else {
+            GameStatus::Ongoing
+        }
+    }
+}
+
+/// Board representations that can be picked when starting the engine or perft
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BoardKind {
+    #[default]
+    Mailbox,
+    Bitboard,
+    Array2D,
+}
+
+impl FromStr for BoardKind {
+    type Err = ChessError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "mailbox" => Ok(BoardKind::Mailbox),
+            "bitboard" => Ok(BoardKind::Bitboard),
+            "array2d" => Ok(BoardKind::Array2D),
+            _ => Err(ChessError::UnknownBoardError {
+                name: s.to_string(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::array2d::Array2D;
+    use crate::board::bitboard::Bitboard;
+    use crate::board::mailbox::Mailbox;
+
+    fn perft<B: Board>(game: &mut B, depth: usize) -> usize {
+        if depth == 0 {
+            return 1;
+        }
+        let mut nodes = 0;
+        for mov in game.get_valid_moves() {
+            game.make_move_in_place(&mov);
+            nodes += perft(game, depth - 1);
+            game.unmake_move();
+        }
+        nodes
+    }
+
+    fn perft_fen<B: Board>(fen: &str, depth: usize) -> usize {
+        perft(&mut B::setup_board(Some(fen)).unwrap(), depth)
+    }
+
+    #[test]
+    fn test_backends_agree_on_perft() {
+        for (fen, depth, expected) in [
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                3,
+                8902,
+            ),
+            (
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+                2,
+                2039,
+            ),
+            ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 3, 2812),
+            (
+                "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+                3,
+                9467,
+            ),
+        ] {
+            assert_eq!(perft_fen::<Mailbox>(fen, depth), expected, "Mailbox {fen}");
+            assert_eq!(
+                perft_fen::<Bitboard>(fen, depth),
+                expected,
+                "Bitboard {fen}"
+            );
+            assert_eq!(perft_fen::<Array2D>(fen, depth), expected, "Array2D {fen}");
+        }
+    }
+
+    fn status<B: Board>(fen: &str, moves: &[&str]) -> GameStatus {
+        let mut game = B::setup_board(Some(fen)).unwrap();
+        for uci in moves {
+            let mov = game
+                .get_valid_moves()
+                .into_iter()
+                .find(|mov| mov.to_string() == *uci)
+                .unwrap();
+            game.make_move_in_place(&mov);
+        }
+        game.game_status()
+    }
+
+    fn assert_status<B: Board>() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(status::<B>(start, &[]), GameStatus::Ongoing);
+        assert_eq!(
+            status::<B>(start, &["f2f3", "e7e5", "g2g4", "d8h4"]),
+            GameStatus::Checkmate
+        );
+        assert_eq!(
+            status::<B>("k7/8/1Q6/8/8/8/8/7K b - - 0 1", &[]),
+            GameStatus::Stalemate
+        );
+        assert_eq!(
+            status::<B>("k7/8/8/8/8/8/8/1R5K w - - 100 80", &[]),
+            GameStatus::FiftyMoveRule
+        );
+        let knights = ["g1f3", "g8f6", "f3g1", "f6g8"];
+        assert_eq!(
+            status::<B>(start, &[&knights[..], &knights[..]].concat()),
+            GameStatus::Repetition
+        );
+        assert_eq!(status::<B>(start, &knights), GameStatus::Ongoing);
+    }
+
+    #[test]
+    fn test_game_status() {
+        assert_status::<Mailbox>();
+        assert_status::<Bitboard>();
+        assert_status::<Array2D>();
+    }
+
+    #[test]
+    fn test_board_kind_from_str() {
+        assert_eq!(
+            BoardKind::from_str("Bitboard").unwrap(),
+            BoardKind::Bitboard
+        );
+        assert_eq!(BoardKind::from_str("array2d").unwrap(), BoardKind::Array2D);
+        assert!(BoardKind::from_str("hexboard").is_err());
+    }
+}
@@ -0,0 +1,394 @@
+use crate::board::fen::parse_square;
+use crate::board::Board;
+use crate::utils::chess_errors::ChessError;
+use crate::utils::gamemove1d::{to_str, CastleTypes, GameMove1d, PassantTypes};
+use crate::utils::pieces::PieceTypes;
+use crate::utils::position::Position;
+
+/// Standard Algebraic Notation for `mov`, which has to be one of `game`'s valid moves
+pub fn to_san<B: Board>(game: &B, mov: &GameMove1d) -> String {
+    let mut san = match mov.castle {
+        Some(CastleTypes::WhiteKing | CastleTypes::BlackKing) => "O-O".to_string(),
+        Some(CastleTypes::WhiteQueen | CastleTypes::BlackQueen) => "O-O-O".to_string(),
+        None => piece_move(game, mov),
+    };
+
+    let next = game.make_move(mov);
+    if next.get_check().is_some() {
+        san.push(if next.get_valid_moves().is_empty() {
+            '#'
+        } else {
+            '+'
+        });
+    }
+    san
+}
+
+/// Finds the valid move of `game` written as `san`. Check marks, annotations like `!?` and
+/// an `e.p.` suffix are ignored, as is a missing capture sign.
+pub fn parse_san<B: Board>(game: &B, san: &str) -> Result<GameMove1d, ChessError> {
+    let malformed = || ChessError::SANParseError {
+        san: san.to_string(),
+    };
+    let text = strip_suffixes(san);
+    if text.is_empty() || !text.is_ascii() {
+        return Err(malformed());
+    }
+
+    let moves = game.get_valid_moves();
+    let candidates: Vec<GameMove1d> = match text {
+        "O-O" | "0-0" => moves
+            .into_iter()
+            .filter(|mov| {
+                matches!(
+                    mov.castle,
+                    Some(CastleTypes::WhiteKing | CastleTypes::BlackKing)
+                )
+            })
+            .collect(),
+        "O-O-O" | "0-0-0" => moves
+            .into_iter()
+            .filter(|mov| {
+                matches!(
+                    mov.castle,
+                    Some(CastleTypes::WhiteQueen | CastleTypes::BlackQueen)
+                )
+            })
+            .collect(),
+        _ => {
+            let pattern = SanPattern::parse(text).ok_or_else(malformed)?;
+            moves
+                .into_iter()
+                .filter(|mov| mov.castle.is_none() && pattern.matches(game, mov))
+                .collect()
+        }
+    };
+
+    match candidates.as_slice() {
+        [mov] => Ok(*mov),
+        [] => Err(ChessError::SANIllegalMoveError {
+            san: san.to_string(),
+        }),
+        _ => {
+            let mut moves: Vec<String> = candidates.iter().map(|mov| to_san(game, mov)).collect();
+            moves.sort();
+            Err(ChessError::SANAmbiguousError {
+                san: san.to_string(),
+                moves: moves.join(", "),
+            })
+        }
+    }
+}
+
+// Everything but castling: piece letter, disambiguation, capture, end square and promotion
+fn piece_move<B: Board>(game: &B, mov: &GameMove1d) -> String {
+    let piece_type = game.piece_at(mov.start).piece_type;
+    let capture = mov.capture
+        || matches!(mov.passant, Some(PassantTypes::PassantCapture(_)))
+        || game.piece_at(mov.end).piece_type != PieceTypes::Empty;
+
+    let mut san = String::new();
+    match piece_letter(piece_type) {
+        // Pawn captures are told apart by the file they come from
+        None if capture => san.push(file(mov.start)),
+        None => {}
+        Some(letter) => {
+            san.push(letter);
+            let rivals: Vec<Position> = game
+                .get_valid_moves()
+                .iter()
+                .filter(|other| {
+                    other.end == mov.end
+                        && other.start != mov.start
+                        && game.piece_at(other.start).piece_type == piece_type
+                })
+                .map(|other| other.start)
+                .collect();
+            if !rivals.is_empty() {
+                if rivals.iter().all(|rival| file(*rival) != file(mov.start)) {
+                    san.push(file(mov.start));
+                } else if rivals.iter().all(|rival| rank(*rival) != rank(mov.start)) {
+                    san.push(rank(mov.start));
+                } else {
+                    san.push_str(&to_str(mov.start.value));
+                }
+            }
+        }
+    }
+    if capture {
+        san.push('x');
+    }
+    san.push_str(&to_str(mov.end.value));
+    if let Some(letter) = mov.promote.and_then(|piece| piece_letter(piece.piece_type)) {
+        san.push('=');
+        san.push(letter);
+    }
+    san
+}
+
+// What a non castling SAN move pins down about the move it stands for
+struct SanPattern {
+    piece_type: PieceTypes,
+    file: Option<char>,
+    rank: Option<char>,
+    end: Position,
+    promote: Option<PieceTypes>,
+}
+
+impl SanPattern {
+    fn parse(text: &str) -> Option<SanPattern> {
+        let (piece_type, rest) = match text.chars().next().and_then(letter_piece) {
+            Some(piece_type) => (piece_type, &text[1..]),
+            None => (PieceTypes::Pawn, text),
+        };
+
+        // Promotions are written e8=Q, and sometimes e8Q
+        let (body, promote) = match rest.split_once('=') {
+            Some((body, letter)) => (body, Some(letter)),
+            None if piece_type == PieceTypes::Pawn
+                && rest.len() > 2
+                && rest.ends_with(|letter: char| "QRBNqrbn".contains(letter)) =>
+            {
+                let (body, letter) = rest.split_at(rest.len() - 1);
+                (body, Some(letter))
+            }
+            None => (rest, None),
+        };
+        let promote = match promote {
+            Some(letter) => {
+                let mut chars = letter.chars();
+                let promote = chars
+                    .next()
+                    .and_then(|letter| letter_piece(letter.to_ascii_uppercase()))
+                    .filter(|promote| *promote != PieceTypes::King)?;
+                if chars.next().is_some() || piece_type != PieceTypes::Pawn {
+                    return None;
+                }
+                Some(promote)
+            }
+            None => None,
+        };
+
+        // Capture signs and the dash of long algebraic moves say nothing the move list doesn't
+        let body: String = body
+            .chars()
+            .filter(|c| !matches!(c, 'x' | ':' | '-'))
+            .collect();
+        if body.len() < 2 {
+            return None;
+        }
+        let (from, to) = body.split_at(body.len() - 2);
+        let end = parse_square(to)?;
+
+        let mut file = None;
+        let mut rank = None;
+        for c in from.chars() {
+            match c {
+                'a'..='h' if file.is_none() && rank.is_none() => file = Some(c),
+                '1'..='8' if rank.is_none() => rank = Some(c),
+                _ => return None,
+            }
+        }
+
+        Some(SanPattern {
+            piece_type,
+            file,
+            rank,
+            end,
+            promote,
+        })
+    }
+
+    fn matches<B: Board>(&self, game: &B, mov: &GameMove1d) -> bool {
+        mov.end == self.end
+            && game.piece_at(mov.start).piece_type == self.piece_type
+            && self
+                .file
+                .is_none_or(|file_char| file(mov.start) == file_char)
+            && self
+                .rank
+                .is_none_or(|rank_char| rank(mov.start) == rank_char)
+            && mov.promote.map(|piece| piece.piece_type) == self.promote
+    }
+}
+
+// Trailing check marks, annotations and en passant suffixes, in any order
+fn strip_suffixes(san: &str) -> &str {
+    let mut text = san.trim();
+    loop {
+        let stripped = text
+            .trim_end_matches(['+', '#', '!', '?'])
+            .trim_end_matches("e.p.")
+            .trim_end();
+        if stripped == text {
+            return text;
+        }
+        text = stripped;
+    }
+}
+
+fn piece_letter(piece_type: PieceTypes) -> Option<char> {
+    match piece_type {
+        PieceTypes::Knight => Some('N'),
+        PieceTypes::Bishop => Some('B'),
+        PieceTypes::Rook => Some('R'),
+        PieceTypes::Queen => Some('Q'),
+        PieceTypes::King => Some('K'),
+        _ => None,
+    }
+}
+
+fn letter_piece(letter: char) -> Option<PieceTypes> {
+    match letter {
+        'N' => Some(PieceTypes::Knight),
+        'B' => Some(PieceTypes::Bishop),
+        'R' => Some(PieceTypes::Rook),
+        'Q' => Some(PieceTypes::Queen),
+        'K' => Some(PieceTypes::King),
+        _ => None,
+    }
+}
+
+fn file(pos: Position) -> char {
+    (b'a' + (pos.value % 10 - 1) as u8) as char
+}
+
+fn rank(pos: Position) -> char {
+    (b'0' + (pos.value / 10 - 1) as u8) as char
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::board::bitboard::Bitboard;
+    use crate::board::fen::START_POSITION;
+    use crate::board::mailbox::Mailbox;
+
+    fn find_uci<B: Board>(game: &B, uci: &str) -> GameMove1d {
+        game.get_valid_moves()
+            .into_iter()
+            .find(|mov| mov.to_string() == uci)
+            .unwrap()
+    }
+
+    // (FEN, UCI move, SAN)
+    const CASES: [(&str, &str, &str); 14] = [
+        (START_POSITION, "g1f3", "Nf3"),
+        (START_POSITION, "e2e4", "e4"),
+        (
+            "rn1qkb1r/ppp1pppp/5n2/3p4/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1",
+            "b8d7",
+            "Nbd7",
+        ),
+        (
+            "rn1qkb1r/ppp1pppp/5n2/3p4/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1",
+            "f6d7",
+            "Nfd7",
+        ),
+        ("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1", "a1a3", "R1a3"),
+        ("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1", "a5a3", "R5a3"),
+        ("4k3/8/8/8/8/Q7/8/Q1Q1K3 w - - 0 1", "a1b2", "Qa1b2"),
+        ("4k3/8/8/8/8/Q7/8/Q1Q1K3 w - - 0 1", "c1b2", "Qcb2"),
+        ("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "e1g1", "O-O"),
+        ("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1", "e8c8", "O-O-O"),
+        ("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6", "exd6"),
+        ("7k/4P3/8/8/8/8/8/4K3 w - - 0 1", "e7e8q", "e8=Q+"),
+        ("6k1/5ppp/8/8/8/8/8/3R2K1 w - - 0 1", "d1d8", "Rd8#"),
+        ("1r4k1/P4ppp/8/8/8/8/8/6K1 w - - 0 1", "a7b8q", "axb8=Q#"),
+    ];
+
+    #[test]
+    fn test_formats_san() {
+        for (fen, uci, san) in CASES {
+            let game = Mailbox::setup_board(Some(fen)).unwrap();
+            assert_eq!(to_san(&game, &find_uci(&game, uci)), san, "{fen}");
+        }
+    }
+
+    #[test]
+    fn test_parses_san() {
+        for (fen, uci, san) in CASES {
+            let game = Mailbox::setup_board(Some(fen)).unwrap();
+            assert_eq!(parse_san(&game, san).unwrap().to_string(), uci, "{fen}");
+        }
+
+        let en_passant = Mailbox::setup_board(Some("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1")).unwrap();
+        for san in ["exd6 e.p.", "exd6e.p.", "ed6", "e5d6", "exd6!?"] {
+            assert_eq!(parse_san(&en_passant, san).unwrap().to_string(), "e5d6");
+        }
+        let promotion = Mailbox::setup_board(Some("7k/4P3/8/8/8/8/8/4K3 w - - 0 1")).unwrap();
+        assert_eq!(parse_san(&promotion, "e8N").unwrap().to_string(), "e7e8n");
+        assert_eq!(parse_san(&promotion, "e8=r").unwrap().to_string(), "e7e8r");
+        let start = Mailbox::setup_board(None).unwrap();
+        assert_eq!(parse_san(&start, "Ng1-f3").unwrap().to_string(), "g1f3");
+        assert_eq!(parse_san(&start, " e4 ").unwrap().to_string(), "e2e4");
+    }
+
+    #[test]
+    fn test_san_errors() {
+        let start = Mailbox::setup_board(None).unwrap();
+        for san in ["", "Zf3", "Nf9", "Nf3=Q", "e8=K", "é4", "N1bf3"] {
+            assert!(
+                matches!(
+                    parse_san(&start, san),
+                    Err(ChessError::SANParseError { .. })
+                ),
+                "{san}"
+            );
+        }
+        for san in ["e5", "Nd2", "O-O", "Ke2", "Bxe4", "e4=Q"] {
+            assert!(
+                matches!(
+                    parse_san(&start, san),
+                    Err(ChessError::SANIllegalMoveError { .. })
+                ),
+                "{san}"
+            );
+        }
+
+        let knights = Mailbox::setup_board(Some(CASES[2].0)).unwrap();
+        match parse_san(&knights, "Nd7") {
+            Err(ChessError::SANAmbiguousError { moves, .. }) => assert_eq!(moves, "Nbd7, Nfd7"),
+            result => panic!("Nd7 gave {result:?}"),
+        }
+        // A pawn reaching the last rank has to say what it promotes to
+        let promotion = Mailbox::setup_board(Some(CASES[11].0)).unwrap();
+        assert!(parse_san(&promotion, "e8").is_err());
+    }
+
+    // Every valid move must print as SAN distinct from the others and parse back to itself
+    fn assert_round_trips<B: Board>(game: &B) {
+        let moves = game.get_valid_moves();
+        let sans: Vec<String> = moves.iter().map(|mov| to_san(game, mov)).collect();
+        for (mov, san) in moves.iter().zip(&sans) {
+            assert_eq!(parse_san(game, san).unwrap(), *mov, "{san}");
+            assert_eq!(sans.iter().filter(|other| *other == san).count(), 1);
+        }
+    }
+
+    #[test]
+    fn test_random_games_round_trip() {
+        let mut rng = StdRng::seed_from_u64(25);
+        for start in [START_POSITION, CASES[2].0, CASES[6].0, CASES[13].0] {
+            for _ in 0..3 {
+                let mut game = Mailbox::setup_board(Some(start)).unwrap();
+                let mut bitboard = Bitboard::setup_board(Some(start)).unwrap();
+                for _ in 0..80 {
+                    assert_round_trips(&game);
+                    let moves = game.get_valid_moves();
+                    let Some(mov) = moves.choose(&mut rng) else {
+                        break;
+                    };
+                    let san = to_san(&game, mov);
+                    assert_eq!(to_san(&bitboard, mov), san);
+                    bitboard.make_move_in_place(&parse_san(&bitboard, &san).unwrap());
+                    game.make_move_in_place(mov);
+                }
+            }
+        }
+    }
+}
@@ -1,41 +1,85 @@
+use std::env;
 use std::str::FromStr;
-use std::sync::mpsc;
-use std::sync::mpsc::Sender;
+use std::sync::Arc;
 use std::thread::JoinHandle;
 use std::{thread, time};
 
 use lazy_static::lazy_static;
 use regex::Regex;
 
+use rusty_chess::ai::engine_options::{parse_setoption, ENGINE_OPTIONS};
 use rusty_chess::ai::manual;
 use rusty_chess::ai::negamax_mailbox::MailboxNegamax;
+use rusty_chess::ai::search_control::{SearchControl, SearchLimits};
+use rusty_chess::ai::time_manager::{TimeControl, TimeManager};
+use rusty_chess::board::array2d::Array2D;
+use rusty_chess::board::bitboard::Bitboard;
 use rusty_chess::board::mailbox::Mailbox;
+use rusty_chess::board::{Board, BoardKind};
 use rusty_chess::utils::gamemove1d::GameMove1d;
 use rusty_chess::utils::pieces::PieceColors;
 
 struct Engine {
     handle: JoinHandle<()>,
-    transmit: Sender<&'static str>,
+    control: Arc<SearchControl>,
 }
 
-fn filter_uci_moves(args: &[&str]) -> Vec<GameMove1d> {
+// Looks a UCI move up among the legal moves, so only moves the board generated are played
+fn find_uci_move<B: Board>(board: &B, uci_move: &str) -> Option<GameMove1d> {
+    board
+        .get_valid_moves()
+        .into_iter()
+        .find(|mv| mv.to_string() == uci_move)
+}
+
+// Plays the moves of a position command in order, returning the first illegal one
+fn apply_uci_moves<B: Board>(mut board: B, args: &[&str]) -> Result<B, String> {
+    let Some(moves_index) = args.iter().position(|&r| r == "moves") else {
+        return Ok(board);
+    };
+    for &input_move in &args[moves_index + 1..] {
+        let Some(mv) = find_uci_move(&board, input_move) else {
+            return Err(input_move.to_string());
+        };
+        board = board.make_move(&mv);
+    }
+    Ok(board)
+}
+
+// Collects the moves after searchmoves up to the next go token, dropping illegal ones
+fn parse_searchmoves<B: Board>(board: &B, args: &[&str]) -> Option<Vec<GameMove1d>> {
     lazy_static! {
-        static ref RE: Regex = Regex::new(r"[abcdefgh]\d[abcdefgh]\d[qrkb]?").unwrap();
+        static ref RE: Regex = Regex::new(r"^[abcdefgh][1-8][abcdefgh][1-8][qrbn]?$").unwrap();
     }
-    args.iter()
-        .filter(|x| RE.is_match(x))
-        .map(|x| GameMove1d::from_str(x).unwrap())
-        .collect()
+    let index = args.iter().position(|&r| r == "searchmoves")?;
+    let moves = args[index + 1..]
+        .iter()
+        .take_while(|x| RE.is_match(x))
+        .filter_map(|&x| {
+            let mv = find_uci_move(board, x);
+            if mv.is_none() {
+                println!("info string illegal move {x}");
+            }
+            mv
+        })
+        .collect();
+    Some(moves)
 }
 
-fn uci_engine() {
+fn uci_engine<B: Board + 'static>() {
     // Print engine id
     println!("id name rustyai");
     println!("id author Corgwn");
 
+    // Declare engine options
+    for option in ENGINE_OPTIONS {
+        println!("{option}");
+    }
+
     // Build Engine structs
-    let mut board = Mailbox::setup_board(None).unwrap();
+    let mut board = B::setup_board(None).unwrap();
     let mut engine_handle: Option<Engine> = None;
+    let mut engine = MailboxNegamax::default();
     let mut _move_count = 0;
 
     // Ready UCI terminal, and start command input
@@ -46,123 +90,85 @@ fn uci_engine() {
         let args: Vec<&str> = command_full.trim().split(' ').collect();
         match args[0] {
             "isready" => println!("readyok"),
-            "setoption" => {}
+            "setoption" => {
+                if let Some((name, value)) = parse_setoption(&args) {
+                    if let Err(error) = engine.set_option(&name, value.as_deref()) {
+                        println!("info string {error}");
+                    }
+                }
+            }
             "register" => {}
             "ucinewgame" => {
-                board = Mailbox::setup_board(None).unwrap();
+                board = B::setup_board(None).unwrap();
+                engine.new_game();
                 _move_count = 0
             }
             "position" if args.contains(&"startpos") => {
-                board = Mailbox::setup_board(None).unwrap();
                 _move_count = 0;
-                if args.contains(&"moves") {
-                    let x = filter_uci_moves(&args);
-                    for input_move in x {
-                        board = board.make_move(&input_move);
-                        _move_count += 1;
-                    }
+                // An illegal move keeps the previous position, the GUI is told why
+                match apply_uci_moves(B::setup_board(None).unwrap(), &args) {
+                    Ok(position) => board = position,
+                    Err(input_move) => println!("info string illegal move {input_move}"),
                 }
             }
             "position" if args.contains(&"fen") => {
-                board = Mailbox::setup_board(Some(args[2])).unwrap();
-                if args.contains(&"moves") {
-                    for input_move in filter_uci_moves(&args) {
-                        board = board.make_move(&input_move);
-                        _move_count += 1;
+                // FEN fields are split on spaces like the rest of the command
+                let fen_end = args
+                    .iter()
+                    .position(|&r| r == "moves")
+                    .unwrap_or(args.len());
+                let fen = args[2..fen_end].join(" ");
+                // A malformed FEN keeps the previous position, the GUI is told why
+                let position = match B::setup_board(Some(&fen)) {
+                    Ok(position) => position,
+                    Err(error) => {
+                        println!("info string {error}");
+                        continue;
                     }
-                }
-            }
-            "go" if args.contains(&"movetime") => {
-                // Find time to move
-                let move_time_index = args.iter().position(|&r| r == "movetime").unwrap();
-                let time_to_move = args[move_time_index + 1].parse().unwrap();
-
-                // Parse searchmoves
-                let searchmoves = if args.contains(&"searchmoves") {
-                    Some(filter_uci_moves(&args))
-                } else {
-                    None
                 };
-
-                // Parse max depth
-                let mut max_plies = None;
-                if let Some(depth_index) = args.iter().position(|&r| r == "depth") {
-                    max_plies = Some(args[depth_index + 1].parse().unwrap());
+                match apply_uci_moves(position, &args) {
+                    Ok(position) => board = position,
+                    Err(input_move) => println!("info string illegal move {input_move}"),
                 }
+            }
+            "go" if args.contains(&"mate") => {
+                // Find number of moves to mate in
+                let mate_index = args.iter().position(|&r| r == "mate").unwrap();
+                let mate_moves = args[mate_index + 1].parse().unwrap();
 
-                // Parse max nodes
-                let mut max_nodes = None;
-                if let Some(max_nodes_index) = args.iter().position(|&r| r == "nodes") {
-                    max_nodes = Some(args[max_nodes_index + 1].parse().unwrap());
-                }
+                // Mate searches are untimed unless a move time is given
+                let move_time = TimeControl::parse(&args, board.get_curr_player()).move_time;
+                let time = TimeManager::new(
+                    &TimeControl {
+                        move_time,
+                        ..Default::default()
+                    },
+                    engine.options.move_overhead,
+                );
 
-                // Start engine and save thread handle to later join if needed
-                let game = board.clone();
-                let (tx, rx) = mpsc::channel();
-                let handle = thread::spawn(move || {
-                    MailboxNegamax::uci_find_move(
-                        game,
-                        time_to_move,
-                        searchmoves,
-                        max_plies,
-                        max_nodes,
-                        rx,
-                    );
-                });
-                engine_handle = Some(Engine {
-                    handle,
-                    transmit: tx,
-                });
-            }
-            "go" if args.contains(&"infinite") => {
                 // Parse searchmoves
-                let searchmoves = if args.contains(&"searchmoves") {
-                    Some(filter_uci_moves(&args))
-                } else {
-                    None
-                };
+                let searchmoves = parse_searchmoves(&board, &args);
 
                 // Start engine and save thread handle to later join if needed
                 let game = board.clone();
-                let (tx, rx) = mpsc::channel();
+                let engine = engine.clone();
+                let control = Arc::new(SearchControl::new(SearchLimits {
+                    time,
+                    ..Default::default()
+                }));
+                let search_control = control.clone();
                 let handle = thread::spawn(move || {
-                    MailboxNegamax::uci_infinite_find_move(game, rx, searchmoves);
-                });
-                engine_handle = Some(Engine {
-                    handle,
-                    transmit: tx,
+                    engine.uci_search_mate(game, searchmoves, mate_moves, &search_control);
                 });
+                engine_handle = Some(Engine { handle, control });
             }
             "go" => {
-                // Find time to move
-                // Get time remaining
-                let time_remaining: u128 = if board.get_curr_player() == PieceColors::White {
-                    let move_time_index = args.iter().position(|&r| r == "wtime").unwrap();
-                    args[move_time_index + 1].parse().unwrap()
-                } else {
-                    let move_time_index = args.iter().position(|&r| r == "btime").unwrap();
-                    args[move_time_index + 1].parse().unwrap()
-                };
-                let increment: u128 = if board.get_curr_player() == PieceColors::White
-                    && args.contains(&"winc")
-                {
-                    let increment_index = args.iter().position(|&r| r == "winc").unwrap();
-                    args[increment_index + 1].parse().unwrap()
-                } else if board.get_curr_player() == PieceColors::Black && args.contains(&"binc") {
-                    let increment_index = args.iter().position(|&r| r == "binc").unwrap();
-                    args[increment_index + 1].parse().unwrap()
-                } else {
-                    0
-                };
-                // Calculate time to search
-                let time_to_move = time_remaining / 20 + increment / 2;
+                // Searches limited only by depth or nodes, or infinite ones, are untimed
+                let control = TimeControl::parse(&args, board.get_curr_player());
+                let time = TimeManager::new(&control, engine.options.move_overhead);
 
                 // Parse searchmoves
-                let searchmoves = if args.contains(&"searchmoves") {
-                    Some(filter_uci_moves(&args))
-                } else {
-                    None
-                };
+                let searchmoves = parse_searchmoves(&board, &args);
 
                 // Parse max depth
                 let mut max_plies = None;
@@ -178,64 +184,78 @@ fn uci_engine() {
 
                 // Start engine and save thread handle to later join if needed
                 let game = board.clone();
-                let (tx, rx) = mpsc::channel();
+                let engine = engine.clone();
+                let control = Arc::new(SearchControl::new(SearchLimits {
+                    time,
+                    max_plies,
+                    max_nodes,
+                    ponder: args.contains(&"ponder"),
+                    infinite: args.contains(&"infinite"),
+                }));
+                let search_control = control.clone();
                 let handle = thread::spawn(move || {
-                    MailboxNegamax::uci_find_move(
-                        game,
-                        time_to_move,
-                        searchmoves,
-                        max_plies,
-                        max_nodes,
-                        rx,
-                    );
-                });
-                engine_handle = Some(Engine {
-                    handle,
-                    transmit: tx,
+                    engine.uci_find_move(game, searchmoves, &search_control);
                 });
+                engine_handle = Some(Engine { handle, control });
             }
             "stop" => {
                 if let Some(x) = engine_handle {
-                    if !x.handle.is_finished() {
-                        let _ = x.transmit.send("stop");
-                        let _ = x.handle.join();
-                    }
+                    x.control.stop();
+                    let _ = x.handle.join();
                     engine_handle = None;
                 }
             }
-            "ponder" => {}
-            "ponderhit" => {}
-            "quit" => break,
+            "ponderhit" => {
+                // The search thread switches to its normal time limits and keeps running
+                if let Some(x) = &engine_handle {
+                    x.control.ponderhit();
+                }
+            }
+            "quit" => {
+                if let Some(x) = engine_handle {
+                    x.control.stop();
+                    let _ = x.handle.join();
+                }
+                break;
+            }
             _ => {}
         }
     }
 }
 
-fn run_sample_game() {
-    let mut game = Mailbox::setup_board(None).unwrap();
+fn run_sample_game<B: Board>() {
+    let mut game = B::setup_board(None).unwrap();
 
     let mut turn_num: usize = 0;
     let mut white_time: u128 = 60000;
     let mut black_time: u128 = 60000;
     let inc: u128 = 600;
+    let engine = MailboxNegamax::default();
     println!("Game starting!");
     println!("{}", game);
     while !game.get_valid_moves().is_empty() {
         let turn = game.get_curr_player();
 
         let turn_start = time::Instant::now();
-        let search_time = match turn {
-            PieceColors::Black => black_time / 20 + inc / 2,
-            PieceColors::White => white_time / 20 + inc / 2,
-            PieceColors::Empty => 0,
+        let control = TimeControl {
+            time: Some(match turn {
+                PieceColors::Black => black_time,
+                _ => white_time,
+            }),
+            increment: inc,
+            ..Default::default()
         };
-        let (_tx, rx) = mpsc::channel();
+        let time = TimeManager::new(&control, engine.options.move_overhead);
         println!(
             "Starting search for player {}, searching for {}ms",
-            turn, search_time
+            turn,
+            time.soft_limit()
         );
-        let next_move =
-            MailboxNegamax::uci_find_move(game.clone(), search_time, None, None, None, rx);
+        let control = SearchControl::new(SearchLimits {
+            time,
+            ..Default::default()
+        });
+        let next_move = engine.uci_find_move(game.clone(), None, &control);
         let turn_duration = turn_start.elapsed().as_millis();
 
         match turn {
@@ -264,8 +284,8 @@ fn run_sample_game() {
         println!("{}", game);
     }
 }
-fn run_manual_game() {
-    let mut game = Mailbox::setup_board(None).unwrap();
+fn run_manual_game<B: Board>() {
+    let mut game = B::setup_board(None).unwrap();
 
     let mut turn_num: usize = 0;
     println!("Game starting!");
@@ -283,22 +303,42 @@ fn run_manual_game() {
     }
 }
 
+// The board representation is picked with `--board <name>`, the mailbox by default
+fn board_kind() -> BoardKind {
+    let args: Vec<String> = env::args().collect();
+    match args.iter().position(|arg| arg == "--board") {
+        Some(index) => match args.get(index + 1).map(|name| BoardKind::from_str(name)) {
+            Some(Ok(kind)) => kind,
+            Some(Err(error)) => panic!("{error}"),
+            None => panic!("--board needs the name of a board representation"),
+        },
+        None => BoardKind::default(),
+    }
+}
+
 fn main() {
-    // let args = Args::parse();
+    match board_kind() {
+        BoardKind::Mailbox => run::<Mailbox>(),
+        BoardKind::Bitboard => run::<Bitboard>(),
+        BoardKind::Array2D => run::<Array2D>(),
+    }
+}
+
+fn run<B: Board + 'static>() {
     loop {
         let mut mode = String::new();
         std::io::stdin().read_line(&mut mode).unwrap();
         match mode.trim() {
             "uci" => {
-                uci_engine();
+                uci_engine::<B>();
                 break;
             }
             "man" => {
-                run_manual_game();
+                run_manual_game::<B>();
                 break;
             }
             "sample" => {
-                run_sample_game();
+                run_sample_game::<B>();
                 break;
             }
             "quit" => break,
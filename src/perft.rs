@@ -1,5 +1,5 @@
 use rusty_chess::{
-    board::mailbox::Mailbox,
+    board::{array2d::Array2D, bitboard::Bitboard, mailbox::Mailbox, Board, BoardKind},
     utils::gamemove1d::{GameMove1d, PassantTypes},
 };
 use std::{env, fmt::Display, ops::AddAssign, str::FromStr};
@@ -71,7 +71,7 @@ fn get_stats(moves: Vec<GameMove1d>) -> PerftStats {
     }
 }
 
-fn perft(depth: usize, game: Mailbox) -> PerftStats {
+fn perft<B: Board>(depth: usize, game: &mut B) -> PerftStats {
     let mut stats: PerftStats = PerftStats {
         nodes: 0,
         captures: 0,
@@ -96,22 +96,39 @@ fn perft(depth: usize, game: Mailbox) -> PerftStats {
     }
 
     for mov in moves {
-        let new_game = game.make_move(&mov);
-        stats += perft(depth - 1, new_game);
+        game.make_move_in_place(&mov);
+        stats += perft(depth - 1, game);
+        game.unmake_move();
     }
 
     stats
 }
 
-fn run_perft_n(n: usize, game: Mailbox) -> PerftStats {
-    perft(n, game)
+fn run_perft_n<B: Board>(n: usize, mut game: B) -> PerftStats {
+    perft(n, &mut game)
 }
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let mut args: Vec<String> = env::args().collect();
+    // `--board <name>` picks the representation and may come anywhere
+    let kind = match args.iter().position(|arg| arg == "--board") {
+        Some(index) => {
+            let name = args.drain(index..index + 2).nth(1).unwrap();
+            BoardKind::from_str(&name).unwrap()
+        }
+        None => BoardKind::default(),
+    };
+    match kind {
+        BoardKind::Mailbox => run::<Mailbox>(&args),
+        BoardKind::Bitboard => run::<Bitboard>(&args),
+        BoardKind::Array2D => run::<Array2D>(&args),
+    }
+}
+
+fn run<B: Board>(args: &[String]) {
     let depth: usize = args[1].parse::<usize>().unwrap();
     let fen: String = args[2].clone();
-    let mut game = Mailbox::setup_board(Some(&fen)).unwrap();
+    let mut game = B::setup_board(Some(&fen)).unwrap();
     if args.len() == 4 {
         let moves: Vec<GameMove1d> = args[3]
             .split(" ")
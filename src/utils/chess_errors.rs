@@ -6,6 +6,50 @@ pub enum ChessError {
     MoveParseAlphaNumError,
     #[snafu(display("Move given is not correct length"))]
     MoveParseLengthError,
-    #[snafu(display("Invalid FEN String"))]
-    FENParseError,
+    #[snafu(display("FEN has {count} fields, expected 6 or 4 without the move counters"))]
+    FENFieldCountError { count: usize },
+    #[snafu(display("FEN piece placement has {count} ranks, expected 8"))]
+    FENRankCountError { count: usize },
+    #[snafu(display(
+        "Rank {rank} of the FEN piece placement covers {squares} squares, expected 8"
+    ))]
+    FENRankLengthError { rank: usize, squares: usize },
+    #[snafu(display("Unknown piece '{symbol}' on rank {rank} of the FEN"))]
+    FENPieceError { symbol: char, rank: usize },
+    #[snafu(display("FEN has {count} {color} kings, expected exactly 1"))]
+    FENKingCountError { color: String, count: usize },
+    #[snafu(display("FEN has a pawn on {square}, pawns cannot stand on the first or last rank"))]
+    FENPawnRankError { square: String },
+    #[snafu(display("Invalid side to move '{value}' in FEN, expected w or b"))]
+    FENSideToMoveError { value: String },
+    #[snafu(display("FEN leaves the {color} king on {square} in check with {side} to move"))]
+    FENOpponentInCheckError {
+        color: String,
+        square: String,
+        side: String,
+    },
+    #[snafu(display("Invalid castling rights '{value}' in FEN, expected - or a subset of KQkq"))]
+    FENCastlingError { value: String },
+    #[snafu(display("FEN castling right {right} needs a king on {king} and a rook on {rook}"))]
+    FENCastlingRightsError {
+        right: char,
+        king: String,
+        rook: String,
+    },
+    #[snafu(display("Invalid en passant square '{value}' in FEN"))]
+    FENEnPassantError { value: String },
+    #[snafu(display("Invalid {field} counter '{value}' in FEN"))]
+    FENMoveCounterError { field: String, value: String },
+    #[snafu(display("'{san}' is not a move in Standard Algebraic Notation"))]
+    SANParseError { san: String },
+    #[snafu(display("{san} is not a legal move in this position"))]
+    SANIllegalMoveError { san: String },
+    #[snafu(display("{san} is ambiguous, it could be any of {moves}"))]
+    SANAmbiguousError { san: String, moves: String },
+    #[snafu(display("No option named {name}"))]
+    UnknownOptionError { name: String },
+    #[snafu(display("Invalid value {value} for option {name}"))]
+    OptionValueError { name: String, value: String },
+    #[snafu(display("No board representation named {name}"))]
+    UnknownBoardError { name: String },
 }
@@ -6,3 +6,4 @@ pub mod gamemove2d;
 pub mod piece_squares;
 pub mod pieces;
 pub mod position;
+pub mod zobrist;
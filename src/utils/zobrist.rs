@@ -0,0 +1,143 @@
+use crate::utils::castling::CastleRights;
+use crate::utils::pieces::{PieceColors, PieceTypes, Pieces};
+use crate::utils::position::Position;
+
+// Fixed seed so that keys are identical between runs, which keeps hash tables and
+// any stored keys reproducible
+const SEED: u64 = 0x9E37_79B9_7F4A_7C15;
+
+pub struct ZobristKeys {
+    pub pieces: [[u64; 64]; 12],
+    pub black_to_move: u64,
+    pub castling: [u64; 4],
+    pub en_passant: [u64; 8],
+}
+
+pub static ZOBRIST: ZobristKeys = ZobristKeys::new();
+
+impl ZobristKeys {
+    const fn new() -> Self {
+        let mut state = SEED;
+        let mut pieces = [[0u64; 64]; 12];
+        let mut piece = 0;
+        while piece < 12 {
+            let mut square = 0;
+            while square < 64 {
+                (state, pieces[piece][square]) = split_mix(state);
+                square += 1;
+            }
+            piece += 1;
+        }
+        let black_to_move;
+        (state, black_to_move) = split_mix(state);
+        let mut castling = [0u64; 4];
+        let mut index = 0;
+        while index < 4 {
+            (state, castling[index]) = split_mix(state);
+            index += 1;
+        }
+        let mut en_passant = [0u64; 8];
+        let mut index = 0;
+        while index < 8 {
+            (state, en_passant[index]) = split_mix(state);
+            index += 1;
+        }
+        ZobristKeys {
+            pieces,
+            black_to_move,
+            castling,
+            en_passant,
+        }
+    }
+
+    /// Key for a piece standing on a 10x12 mailbox square, 0 for empty or offboard squares
+    pub fn piece(&self, piece: Pieces, pos: Position) -> u64 {
+        match piece_index(piece) {
+            Some(index) => self.pieces[index][square_index(pos)],
+            None => 0,
+        }
+    }
+
+    /// Combined key for every castling right that is still available
+    pub fn castle_rights(&self, rights: CastleRights) -> u64 {
+        let mut key = 0;
+        for (available, value) in [
+            rights.white_king,
+            rights.white_queen,
+            rights.black_king,
+            rights.black_queen,
+        ]
+        .iter()
+        .zip(self.castling)
+        {
+            if *available {
+                key ^= value;
+            }
+        }
+        key
+    }
+
+    /// Key for the file of an en passant target square, 0 if there is none
+    pub fn en_passant(&self, en_passant: Option<Position>) -> u64 {
+        match en_passant {
+            Some(pos) => self.en_passant[square_index(pos) % 8],
+            None => 0,
+        }
+    }
+}
+
+pub(crate) const fn split_mix(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    (state, z ^ (z >> 31))
+}
+
+fn piece_index(piece: Pieces) -> Option<usize> {
+    let type_index = match piece.piece_type {
+        PieceTypes::Pawn => 0,
+        PieceTypes::Knight => 1,
+        PieceTypes::Bishop => 2,
+        PieceTypes::Rook => 3,
+        PieceTypes::Queen => 4,
+        PieceTypes::King => 5,
+        PieceTypes::Empty | PieceTypes::Offboard => return None,
+    };
+    match piece.color {
+        PieceColors::White => Some(type_index),
+        PieceColors::Black => Some(type_index + 6),
+        PieceColors::Empty => None,
+    }
+}
+
+// Converts a 10x12 mailbox index into a 0-63 square index, a1 = 0 and h8 = 63
+pub(crate) fn square_index(pos: Position) -> usize {
+    let row = pos.value / 10 - 2;
+    let col = pos.value % 10 - 1;
+    row * 8 + col
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_square_index() {
+        assert_eq!(square_index(Position { value: 21 }), 0);
+        assert_eq!(square_index(Position { value: 28 }), 7);
+        assert_eq!(square_index(Position { value: 98 }), 63);
+    }
+
+    #[test]
+    fn test_keys_are_unique() {
+        let mut keys: Vec<u64> = ZOBRIST.pieces.iter().flatten().copied().collect();
+        keys.push(ZOBRIST.black_to_move);
+        keys.extend(ZOBRIST.castling);
+        keys.extend(ZOBRIST.en_passant);
+        let count = keys.len();
+        keys.sort();
+        keys.dedup();
+        assert_eq!(keys.len(), count);
+    }
+}